@@ -120,14 +120,21 @@ config.scrollback_lines = 10000
 
 config.hyperlink_rules = wezterm.default_hyperlink_rules()
 
--- Make file paths clickable (with optional :line:col suffix)
+-- Make file paths clickable (with optional :line:col or (line,col) suffix, or a range
+-- like :10-20, :10:5-12:8 or #L10-L20)
 -- Use custom scheme so WezTerm doesn't handle it internally
 -- Matches:
---   - Prefixed paths: ./relative, ../relative, /absolute, ~/home, ~user/home (extension optional)
+--   - Prefixed paths: ./relative, ../relative, /absolute, ~/home, ~user/home,
+--     $VAR/x, ${VAR}/x, ${VAR:-default}/x (extension optional)
 --   - Unprefixed paths: must contain / AND file extension (e.g., src/main.rs)
+--   - Bare file names only with a location that can't be prose: tsc/MSVC style
+--     `File.cpp(42):` / `app.ts(10,5):`, or a JVM frame's `(Foo.java:42)`
+--   - The path of a file:// URL, e.g. in Node's `at fn (file:///app/x.mjs:3:1)`
+--   - Bare file names followed by a line in words: `File "x.py", line 3`,
+--     `on main.tf line 12`; the handler reads the line from the clicked text
 -- Supports quoted/unquoted paths, paths in parentheses, stack traces with :in, etc.
 table.insert(config.hyperlink_rules, {
-  regex = [[(?<=^|[\s"'(])((?:(?:\.\.?|~[A-Za-z0-9_-]*)?/[^\s"'():,]+|[A-Za-z0-9_][A-Za-z0-9_/-]*/[^\s"'():,]*\.[A-Za-z0-9]+)(?::\d+(?::\d+)?)?)(?=[\s"'():,]|$)]],
+  regex = [[(?<=^|[\s"'(]|file://)((?:(?:\.\.?|~[A-Za-z0-9_-]*|\$(?:[A-Za-z_][A-Za-z0-9_]*|\{[A-Za-z_][A-Za-z0-9_]*(?::?-[^\s"'(){}]*)?\}))?/[^\s"'():,]+|[A-Za-z0-9_][A-Za-z0-9_/-]*/[^\s"'():,]*\.[A-Za-z0-9]+)(?::\d+(?::\d+)?(?:-\d+(?::\d+)?)?|\(\d+(?:, ?\d+)?\)|#L\d+(?:C\d+)?(?:-L\d+(?:C\d+)?)?)?|[A-Za-z0-9_][A-Za-z0-9_.-]*\.[A-Za-z0-9]+(?:\(\d+(?:, ?\d+)?\)(?=:)|:\d+(?::\d+)?(?=\))|(?="?,? [Ll]ine \d)))(?=[\s"'():,]|$)]],
   format = 'openineditor:$1',
 })
 
@@ -153,24 +160,121 @@ config.mouse_bindings = {
   },
 }
 
+-- Build a file handler command line, passing the pane's user vars so $NAME in the
+-- path expands the way it would in that pane
+local function handler_args(pane, args)
+  local command = { '@fileHandler@' }
+  for name, value in pairs(pane:get_user_vars()) do
+    -- Only names that $NAME can reference; the handler ignores the rest anyway
+    if name:match '^[%a_][%w_]*$' then
+      table.insert(command, '--env')
+      table.insert(command, name .. '=' .. value)
+    end
+  end
+  for _, arg in ipairs(args) do
+    table.insert(command, arg)
+  end
+  return command
+end
+
+-- The visible line a clicked path was printed on, so the handler can read locations
+-- written in words next to it (e.g. `File "x.py", line 42`). open-uri doesn't say
+-- which line was clicked, so when several lines show the path (repeated stack frames)
+-- no context is passed rather than guessing.
+local function line_containing(pane, path)
+  local text = pane:get_lines_as_text(pane:get_dimensions().viewport_rows)
+  local found = nil
+  for line in text:gmatch '[^\n]+' do
+    if line:find(path, 1, true) then
+      if found then
+        return ''
+      end
+      found = line
+    end
+  end
+  return found or ''
+end
+
+-- Open a path (or a candidate chosen below) with the Rust file handler
+local function open_with_handler(pane, path, cwd_path, context)
+  wezterm.run_child_process(handler_args(pane, {
+    'open', '--cwd', cwd_path, '--context', context, '--', path,
+  }))
+end
+
+-- The pane's working directory as a path, or '' when WezTerm doesn't know it
+local function pane_cwd(pane)
+  local cwd = pane:get_current_working_dir()
+  if not cwd then
+    return ''
+  end
+  -- cwd is a URL object, convert to string and get the file path
+  return cwd.file_path or tostring(cwd):sub(8) -- strip file:// prefix if string
+end
+
+-- GitHub (/blob/, /tree/), GitLab (/-/blob/, /-/tree/) and Gitea (/src/branch|tag|commit/)
+-- file URLs, which the handler opens in the local clone
+local function is_forge_file_url(uri)
+  return uri:match '^https?://[^/]+/[^/]+/[^/]+/blob/' ~= nil
+    or uri:match '^https?://[^/]+/[^/]+/[^/]+/tree/' ~= nil
+    or uri:match '^https?://[^/]+/.+/%-/blob/' ~= nil
+    or uri:match '^https?://[^/]+/.+/%-/tree/' ~= nil
+    or uri:match '^https?://[^/]+/[^/]+/[^/]+/src/branch/' ~= nil
+    or uri:match '^https?://[^/]+/[^/]+/[^/]+/src/tag/' ~= nil
+    or uri:match '^https?://[^/]+/[^/]+/[^/]+/src/commit/' ~= nil
+end
+
 -- Open openineditor: URIs by delegating to our Rust file handler
 wezterm.on('open-uri', function(window, pane, uri)
+  -- The handler falls back to the browser when there is no clone of the repository
+  if is_forge_file_url(uri) then
+    open_with_handler(pane, uri, pane_cwd(pane), '')
+    return false
+  end
+
   if uri:sub(1, 13) == 'openineditor:' then
     local path = uri:sub(14)
+    local cwd_path = pane_cwd(pane)
+
+    -- Ask the handler for every file the path could refer to. When there is more
+    -- than one, let the user pick instead of opening the handler's best guess.
+    local context = line_containing(pane, path)
+    local ok, stdout = wezterm.run_child_process(handler_args(pane, {
+      'candidates', '--format', 'json', '--cwd', cwd_path, '--context', context, '--', path,
+    }))
+    local candidates = {}
+    if ok then
+      local parsed_ok, result = pcall(wezterm.json_parse, stdout)
+      if parsed_ok and result and result.candidates then
+        candidates = result.candidates
+      end
+    end
 
-    -- Get the current working directory
-    local cwd = pane:get_current_working_dir()
-    local cwd_path = ''
-
-    if cwd then
-      -- cwd is a URL object, convert to string and get the file path
-      local cwd_str = tostring(cwd)
-      cwd_path = cwd.file_path or cwd_str:sub(8) -- strip file:// prefix if string
+    if #candidates > 1 then
+      local choices = {}
+      for _, candidate in ipairs(candidates) do
+        table.insert(choices, { id = candidate.input, label = candidate.label })
+      end
+
+      window:perform_action(
+        act.InputSelector {
+          title = 'Open which file?',
+          choices = choices,
+          fuzzy = true,
+          action = wezterm.action_callback(function(_, _, id, _)
+            if id then
+              open_with_handler(pane, id, cwd_path, context)
+            end
+          end),
+        },
+        pane
+      )
+      return false
     end
 
     -- Delegate to the Rust file handler for all the complex logic
     -- The handler will: resolve paths, check existence, and open appropriately
-    wezterm.run_child_process { '@fileHandler@', path, cwd_path }
+    open_with_handler(pane, path, cwd_path, context)
 
     return false
   end
@@ -23,6 +23,9 @@ let
       lockFile = ./file-handler/Cargo.lock;
     };
 
+    # The index, worktree and forge tests create throwaway git repos
+    nativeCheckInputs = [ pkgs.git ];
+
     installPhase = ''
       mkdir -p $out/bin
       cp target/*/release/wezterm-file-handler $out/bin/
@@ -0,0 +1,336 @@
+use std::path::PathBuf;
+
+use crate::config::Overrides;
+
+pub const USAGE: &str = "\
+Usage: wezterm-file-handler [OPTIONS] <COMMAND> [INPUT] [CWD]
+       wezterm-file-handler [--dry-run] <INPUT> <CWD>
+
+Resolve a path printed in the terminal (e.g. \"src/main.rs:42:7\") and open it.
+GitHub, GitLab and Gitea file URLs open in the local clone when there is one.
+
+Commands:
+  open      Resolve INPUT and open it in an editor or the system opener (default)
+  resolve   Print the absolute path INPUT resolves to
+  parse     Print the path and location parsed from INPUT
+  explain   Show every decision made while resolving and opening INPUT
+  candidates
+            List every existing file INPUT could refer to, best first
+  config    Print the effective configuration
+
+Options:
+      --cwd <DIR>      Directory relative paths are resolved against
+                       (default: CWD argument, else the current directory)
+      --context <TEXT> The line of output INPUT was found in, for JVM stack frames
+                       and locations written in words (e.g. `File \"x.py\", line 42`)
+      --editor <CMD>   Editor command, overriding the config, $VISUAL and $EDITOR
+      --config <FILE>  Config file to use instead of the user config
+      --env <NAME=VALUE>
+                       Variable for expanding $NAME in INPUT, taking precedence
+                       over the config and environment (repeatable)
+      --dry-run        Print the command instead of running it (open)
+      --format <FMT>   Output format: text (default) or json; json applies to
+                       parse, resolve, explain, candidates, config and
+                       open --dry-run
+  -h, --help           Print this help
+      --               Treat every following argument as INPUT or CWD
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Open,
+    Resolve,
+    Parse,
+    Explain,
+    Candidates,
+    Config,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "open" => Some(Command::Open),
+            "resolve" => Some(Command::Resolve),
+            "parse" => Some(Command::Parse),
+            "explain" => Some(Command::Explain),
+            "candidates" => Some(Command::Candidates),
+            "config" => Some(Command::Config),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// Parsed command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    pub input: Option<String>,
+    pub cwd: Option<String>,
+    /// The line of terminal output the input came from
+    pub context: Option<String>,
+    pub dry_run: bool,
+    pub format: Format,
+    pub help: bool,
+    /// Settings that take precedence over the environment and config files
+    pub overrides: Overrides,
+}
+
+impl Cli {
+    /// Parse arguments, not including the program name
+    ///
+    /// Without a subcommand this is the form `config.lua` has always used:
+    /// `[--dry-run] <INPUT> <CWD>`, which behaves like `open`.
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        let mut command = None;
+        let mut positional = Vec::new();
+        let mut cli = Cli {
+            command: Command::Open,
+            input: None,
+            cwd: None,
+            context: None,
+            dry_run: false,
+            format: Format::Text,
+            help: false,
+            overrides: Overrides::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = |name: &str| {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+
+            match flag {
+                "--" => {
+                    positional.extend(args.by_ref());
+                    break;
+                }
+                "-h" | "--help" => cli.help = true,
+                "--dry-run" => cli.dry_run = true,
+                "--format" => {
+                    cli.format = match value("--format")?.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        other => {
+                            return Err(format!(
+                                "Unknown format '{}' (expected text or json)",
+                                other
+                            ))
+                        }
+                    }
+                }
+                "--cwd" => cli.cwd = Some(value("--cwd")?),
+                "--context" => cli.context = Some(value("--context")?),
+                "--editor" => cli.overrides.editor = Some(value("--editor")?),
+                "--config" => cli.overrides.config_file = Some(PathBuf::from(value("--config")?)),
+                "--env" => {
+                    let var = value("--env")?;
+                    let (name, value) = var
+                        .split_once('=')
+                        .ok_or_else(|| format!("--env expects NAME=VALUE, got '{}'", var))?;
+                    cli.overrides
+                        .vars
+                        .push((name.to_string(), value.to_string()));
+                }
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("Unknown option '{}'", arg));
+                }
+                _ => {
+                    // Only the first positional can name a subcommand
+                    match Command::from_name(&arg) {
+                        Some(found) if command.is_none() && positional.is_empty() => {
+                            command = Some(found)
+                        }
+                        _ => positional.push(arg),
+                    }
+                }
+            }
+        }
+
+        if let Some(command) = command {
+            cli.command = command;
+        }
+        if cli.help {
+            return Ok(cli);
+        }
+
+        let mut positional = positional.into_iter();
+        cli.input = positional.next();
+        if let Some(cwd) = positional.next() {
+            if cli.cwd.is_some() {
+                return Err("CWD given both as an argument and with --cwd".to_string());
+            }
+            cli.cwd = Some(cwd);
+        }
+        if let Some(extra) = positional.next() {
+            return Err(format!("Unexpected argument '{}'", extra));
+        }
+
+        match (cli.command, &cli.input) {
+            (Command::Config, Some(input)) => {
+                // `config [CWD]`: the one positional is the directory to load project config for
+                if cli.cwd.is_some() {
+                    return Err(format!("Unexpected argument '{}'", input));
+                }
+                cli.cwd = cli.input.take();
+            }
+            (Command::Config, None) => {}
+            (_, None) => return Err("Missing INPUT".to_string()),
+            _ => {}
+        }
+        if cli.dry_run && cli.command != Command::Open {
+            return Err("--dry-run only applies to the open command".to_string());
+        }
+        if cli.format == Format::Json && cli.command == Command::Open && !cli.dry_run {
+            return Err("--format json needs --dry-run when opening".to_string());
+        }
+
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::parse(args.iter().copied()).unwrap()
+    }
+
+    fn parse_err(args: &[&str]) -> String {
+        Cli::parse(args.iter().copied()).unwrap_err()
+    }
+
+    #[test]
+    fn test_legacy_form() {
+        let cli = parse(&["src/main.rs:3", "/project"]);
+        assert_eq!(cli.command, Command::Open);
+        assert_eq!(cli.input.as_deref(), Some("src/main.rs:3"));
+        assert_eq!(cli.cwd.as_deref(), Some("/project"));
+        assert!(!cli.dry_run);
+
+        let cli = parse(&["--dry-run", "src/main.rs:3", "/project"]);
+        assert!(cli.dry_run);
+        assert_eq!(cli.input.as_deref(), Some("src/main.rs:3"));
+    }
+
+    #[test]
+    fn test_flags_after_positionals() {
+        let cli = parse(&["src/main.rs:3", "/project", "--dry-run"]);
+        assert!(cli.dry_run);
+        assert_eq!(cli.input.as_deref(), Some("src/main.rs:3"));
+        assert_eq!(cli.cwd.as_deref(), Some("/project"));
+    }
+
+    #[test]
+    fn test_double_dash_allows_dash_paths() {
+        let cli = parse(&["open", "--cwd", "/project", "--", "--dry-run"]);
+        assert_eq!(cli.input.as_deref(), Some("--dry-run"));
+        assert!(!cli.dry_run);
+
+        // A file named after a subcommand
+        let cli = parse(&["open", "--", "resolve", "/project"]);
+        assert_eq!(cli.input.as_deref(), Some("resolve"));
+        assert_eq!(cli.cwd.as_deref(), Some("/project"));
+    }
+
+    #[test]
+    fn test_subcommands_and_options() {
+        let cli = parse(&["resolve", "--cwd=/project", "lib/x.rb"]);
+        assert_eq!(cli.command, Command::Resolve);
+        assert_eq!(cli.cwd.as_deref(), Some("/project"));
+
+        let cli = parse(&[
+            "explain", "--editor", "nvim", "--config", "/c.toml", "x.rb:1",
+        ]);
+        assert_eq!(cli.command, Command::Explain);
+        assert_eq!(cli.overrides.editor.as_deref(), Some("nvim"));
+        assert_eq!(cli.overrides.config_file, Some(PathBuf::from("/c.toml")));
+
+        let cli = parse(&["open", "--context", "  File \"x.py\", line 3", "x.py"]);
+        assert_eq!(cli.context.as_deref(), Some("  File \"x.py\", line 3"));
+
+        let cli = parse(&["config", "/project"]);
+        assert_eq!(cli.command, Command::Config);
+        assert_eq!(cli.cwd.as_deref(), Some("/project"));
+        assert_eq!(cli.input, None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(parse(&["resolve", "x"]).format, Format::Text);
+        assert_eq!(
+            parse(&["resolve", "--format", "json", "x"]).format,
+            Format::Json
+        );
+        assert_eq!(
+            parse(&["--dry-run", "--format=json", "x", "/p"]).format,
+            Format::Json
+        );
+
+        assert_eq!(
+            parse_err(&["resolve", "--format", "yaml", "x"]),
+            "Unknown format 'yaml' (expected text or json)"
+        );
+        assert_eq!(
+            parse_err(&["open", "--format", "json", "x"]),
+            "--format json needs --dry-run when opening"
+        );
+    }
+
+    #[test]
+    fn test_env_vars() {
+        let cli = parse(&["--env", "ROOT=/a=b", "--env=EMPTY=", "x", "/p"]);
+        assert_eq!(
+            cli.overrides.vars,
+            [
+                ("ROOT".to_string(), "/a=b".to_string()),
+                ("EMPTY".to_string(), String::new())
+            ]
+        );
+
+        assert_eq!(
+            parse_err(&["--env", "ROOT", "x"]),
+            "--env expects NAME=VALUE, got 'ROOT'"
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        assert!(parse(&["--help"]).help);
+        assert!(parse(&["open", "-h"]).help);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_err(&[]), "Missing INPUT");
+        assert_eq!(parse_err(&["--bogus", "x"]), "Unknown option '--bogus'");
+        assert_eq!(parse_err(&["open", "--cwd"]), "--cwd requires a value");
+        assert_eq!(parse_err(&["a", "b", "c"]), "Unexpected argument 'c'");
+        assert_eq!(
+            parse_err(&["a", "b", "--cwd", "c"]),
+            "CWD given both as an argument and with --cwd"
+        );
+        assert_eq!(
+            parse_err(&["resolve", "--dry-run", "x"]),
+            "--dry-run only applies to the open command"
+        );
+    }
+}
@@ -0,0 +1,1002 @@
+//! Configuration loaded from TOML files, the environment and the command line
+//!
+//! Sources, from lowest to highest precedence:
+//!
+//! 1. Built-in defaults
+//! 2. User config: `$XDG_CONFIG_HOME/wezterm-file-handler/config.toml`
+//!    (or `$WEZTERM_FILE_HANDLER_CONFIG`)
+//! 3. Project config: the nearest `.wezterm-file-handler.toml` in the cwd or its ancestors
+//! 4. Environment: `WEZTERM_FILE_HANDLER_EDITOR`, `WEZTERM_FILE_HANDLER_OPENER`,
+//!    `WEZTERM_FILE_HANDLER_LOG`
+//! 5. Command line overrides, including variables the pane passes with `--env`
+//!
+//! Scalar settings (and `strategies`) from a higher layer replace lower ones. List
+//! settings (`rules`, `rewrites`, `search_roots`, `flake_checkouts`, `clone_roots`) are
+//! concatenated with higher layers first, so a project rule wins over a user rule for the
+//! same file. `vars` are merged by name, the higher layer winning.
+//!
+//! ```toml
+//! editor = "nvim"
+//! system_opener = "xdg-open"
+//! search_roots = ["~/Code"]
+//! strategies = ["cwd", "ancestors", "search_roots", "suffix"]
+//! cache_dir = "~/.cache/wezterm-file-handler"
+//! normalization = "lexical"  # or "physical" to follow symlinks before `..`
+//! expand_vars = true  # expand $VAR, ${VAR} and ${VAR:-default} in paths
+//! flake_checkouts = ["~/nix-config"]  # where /nix/store/<hash>-source/ paths are opened
+//! open_nix_store = false  # true to open store paths as printed
+//! map_worktrees = false  # map paths in sibling git worktrees onto the pane's worktree
+//! infer_rewrites = true  # map missing absolute paths onto the cwd's repo by their tail
+//! case_insensitive = true  # retry missing paths ignoring case, e.g. App/Models/User.rb
+//! did_you_mean = "suggest"  # report the closest file when the path is missing;
+//!                           # "open" opens a clear winner, "off" skips the search
+//! directories = "reveal"  # or "workspace" (editor), "tab" or "pane" (wezterm cli)
+//! source_maps = true  # open the original source for locations in generated .js files
+//! rust_sysroot = "~/.rustup/toolchains/stable-x86_64-unknown-linux-gnu"  # else `rustc --print sysroot`
+//! clone_roots = ["~/Code"]  # where GitHub/GitLab/Gitea URLs find local clones
+//!
+//! [[rules]]
+//! extension = "png"
+//! opener = "system"
+//!
+//! [[rules]]
+//! glob = "docs/**/*.md"
+//! opener = "zed"
+//!
+//! [[rewrites]]
+//! from = "/home/runner/work/repo/repo"
+//! to = "~/Code/github.com/org/repo"
+//!
+//! [[rewrites]]
+//! regex = "^/Users/([^/]+)/Code"
+//! to = "/home/$1/Code"
+//!
+//! [vars]
+//! PROJECT_ROOT = "~/Code/github.com/org/repo"
+//!
+//! [log]
+//! enabled = true
+//! file = "/tmp/wezterm-file-handler.log"
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::expand::{env_dir, expand_vars};
+use crate::fuzzy::DidYouMean;
+use crate::index::default_cache_dir;
+use crate::opener::{DirectoryAction, Editor, SYSTEM_OPENER_ENV};
+use crate::resolver::{Strategy, DEFAULT_STRATEGIES};
+use crate::{resolve_path, Normalization};
+
+/// Name of the per-project config file, looked up from the cwd towards the root
+pub const PROJECT_CONFIG_FILE: &str = ".wezterm-file-handler.toml";
+
+pub const CONFIG_ENV: &str = "WEZTERM_FILE_HANDLER_CONFIG";
+pub const EDITOR_ENV: &str = "WEZTERM_FILE_HANDLER_EDITOR";
+pub const LOG_ENV: &str = "WEZTERM_FILE_HANDLER_LOG";
+
+pub const DEFAULT_LOG_FILE: &str = "/tmp/wezterm-file-handler.log";
+
+/// The effective configuration after all layers are merged
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Config {
+    /// Editor command used for files with a location, e.g. "code" or "emacsclient -n"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    /// Command that opens files with the system default application
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_opener: Option<String>,
+    /// Extra directories that relative paths are resolved against
+    pub search_roots: Vec<PathBuf>,
+    /// Resolution strategies, tried in order until a candidate exists
+    pub strategies: Vec<Strategy>,
+    /// Whether `..` is resolved textually or through symlinks
+    pub normalization: Normalization,
+    /// Whether missing paths are retried with case-insensitive name matching
+    pub case_insensitive: bool,
+    /// What to do when a path doesn't exist but a similar one does
+    pub did_you_mean: DidYouMean,
+    /// Where repository file indexes are cached; None disables the cache
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// Per-file opener choices; the first matching rule wins
+    pub rules: Vec<OpenerRule>,
+    /// How paths that resolve to a directory are opened
+    pub directories: DirectoryAction,
+    /// Path prefix and regex rewrites; the first that matches is applied before resolving
+    pub rewrites: Vec<Rewrite>,
+    /// Whether missing absolute paths are mapped onto the cwd's repo by their tail
+    pub infer_rewrites: bool,
+    /// Whether paths in another worktree of the cwd's repo open in the cwd's worktree
+    pub map_worktrees: bool,
+    /// Whether shell-style variables in paths are expanded
+    pub expand_vars: bool,
+    /// Variables for expansion, checked before the handler's environment
+    pub vars: BTreeMap<String, String>,
+    /// Local flake checkouts that flake source store paths are mapped onto
+    pub flake_checkouts: Vec<PathBuf>,
+    /// Open `/nix/store` paths as printed instead of mapping them to a checkout
+    pub open_nix_store: bool,
+    /// Whether locations in generated JavaScript are mapped through source maps
+    pub source_maps: bool,
+    /// Toolchain whose `rust-src` `/rustc/<hash>/` paths map to; None asks rustc
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_sysroot: Option<PathBuf>,
+    /// Directories holding clones that forge URLs open in, e.g. `~/Code` for
+    /// `~/Code/github.com/org/repo`
+    pub clone_roots: Vec<PathBuf>,
+    pub log: LogConfig,
+    /// Where each layer came from, in the order it was applied
+    #[serde(skip)]
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogConfig {
+    pub enabled: bool,
+    pub file: PathBuf,
+}
+
+/// Choose an opener for files matching a glob or extension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenerRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+    /// "editor" for the default editor, "system" for the system opener, or an editor command
+    pub opener: String,
+}
+
+/// Replace a leading path prefix, e.g. a CI checkout directory with the local clone,
+/// or the first match of a regex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rewrite {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Replacement; with `regex`, `$1` and `${name}` refer to capture groups
+    pub to: String,
+    /// `regex`, compiled once when the config is loaded
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+impl PartialEq for Rewrite {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.from, &self.regex, &self.to) == (&other.from, &other.regex, &other.to)
+    }
+}
+
+/// Settings that come from the environment or the command line rather than a file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub config_file: Option<PathBuf>,
+    pub editor: Option<String>,
+    pub system_opener: Option<String>,
+    pub log_file: Option<PathBuf>,
+    /// Variables supplied by the pane, as (name, value)
+    pub vars: Vec<(String, String)>,
+}
+
+/// A problem with one configuration source
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// The file or layer the error came from
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// One config file as written, before merging
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    editor: Option<String>,
+    system_opener: Option<String>,
+    #[serde(default)]
+    search_roots: Vec<String>,
+    strategies: Option<Vec<Strategy>>,
+    cache_dir: Option<String>,
+    normalization: Option<Normalization>,
+    case_insensitive: Option<bool>,
+    did_you_mean: Option<DidYouMean>,
+    #[serde(default)]
+    rules: Vec<OpenerRule>,
+    directories: Option<DirectoryAction>,
+    #[serde(default)]
+    rewrites: Vec<Rewrite>,
+    infer_rewrites: Option<bool>,
+    map_worktrees: Option<bool>,
+    expand_vars: Option<bool>,
+    #[serde(default)]
+    vars: BTreeMap<String, String>,
+    #[serde(default)]
+    flake_checkouts: Vec<String>,
+    open_nix_store: Option<bool>,
+    source_maps: Option<bool>,
+    rust_sysroot: Option<String>,
+    #[serde(default)]
+    clone_roots: Vec<String>,
+    log: Option<LogFile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogFile {
+    enabled: Option<bool>,
+    file: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            editor: None,
+            system_opener: None,
+            search_roots: Vec::new(),
+            strategies: DEFAULT_STRATEGIES.to_vec(),
+            normalization: Normalization::Lexical,
+            case_insensitive: true,
+            did_you_mean: DidYouMean::default(),
+            cache_dir: default_cache_dir(),
+            rules: Vec::new(),
+            directories: DirectoryAction::default(),
+            rewrites: Vec::new(),
+            infer_rewrites: true,
+            map_worktrees: false,
+            expand_vars: true,
+            vars: BTreeMap::new(),
+            flake_checkouts: Vec::new(),
+            open_nix_store: false,
+            source_maps: true,
+            rust_sysroot: None,
+            clone_roots: Vec::new(),
+            log: LogConfig {
+                enabled: true,
+                file: PathBuf::from(DEFAULT_LOG_FILE),
+            },
+            sources: vec!["defaults".to_string()],
+        }
+    }
+}
+
+impl Overrides {
+    /// Read the handler's own environment variables
+    pub fn from_env() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        Overrides {
+            config_file: var(CONFIG_ENV).map(PathBuf::from),
+            editor: var(EDITOR_ENV),
+            system_opener: var(SYSTEM_OPENER_ENV),
+            log_file: var(LOG_ENV).map(PathBuf::from),
+            vars: Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Overrides::default()
+    }
+}
+
+impl Config {
+    /// Load and merge every layer for a handler invoked in `cwd`
+    pub fn load(cwd: &Path, cli: &Overrides) -> Result<Self, ConfigError> {
+        let env = Overrides::from_env();
+        let mut config = Config::default();
+
+        // An explicitly named config file must exist; the default location is optional
+        match cli.config_file.as_ref().or(env.config_file.as_ref()) {
+            Some(path) => config.merge_file(path)?,
+            None => {
+                if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+                    config.merge_file(&path)?;
+                }
+            }
+        }
+
+        if let Some(path) = find_project_config(cwd) {
+            config.merge_file(&path)?;
+        }
+
+        config.apply_overrides(&env, "environment")?;
+        config.apply_overrides(cli, "command line")?;
+        Ok(config)
+    }
+
+    /// Parse, validate and merge one config file on top of this config
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let source = path.display().to_string();
+        let contents = fs::read_to_string(path).map_err(|e| ConfigError {
+            source: source.clone(),
+            message: e.to_string(),
+        })?;
+
+        let base_dir = path.parent().unwrap_or(Path::new("/"));
+        self.merge_str(&contents, &source, base_dir)
+    }
+
+    /// Merge TOML `contents`; relative paths in it are taken relative to `base_dir`
+    pub fn merge_str(
+        &mut self,
+        contents: &str,
+        source: &str,
+        base_dir: &Path,
+    ) -> Result<(), ConfigError> {
+        let error = |message: String| ConfigError {
+            source: source.to_string(),
+            message,
+        };
+
+        let mut file: ConfigFile = toml::from_str(contents).map_err(|e| error(e.to_string()))?;
+
+        if let Some(editor) = &file.editor {
+            validate_editor(editor).map_err(|msg| error(format!("editor: {}", msg)))?;
+        }
+        if let Some(opener) = &file.system_opener {
+            if opener.trim().is_empty() {
+                return Err(error("system_opener: must not be empty".to_string()));
+            }
+        }
+        for (i, root) in file.search_roots.iter().enumerate() {
+            if root.trim().is_empty() {
+                return Err(error(format!("search_roots[{}]: must not be empty", i)));
+            }
+        }
+        if let Some(strategies) = &file.strategies {
+            if strategies.is_empty() {
+                return Err(error(
+                    "strategies: must list at least one strategy".to_string(),
+                ));
+            }
+            for (i, strategy) in strategies.iter().enumerate() {
+                if strategies[..i].contains(strategy) {
+                    return Err(error(format!(
+                        "strategies[{}]: '{}' is listed more than once",
+                        i,
+                        strategy.name()
+                    )));
+                }
+            }
+        }
+        for (i, checkout) in file.flake_checkouts.iter().enumerate() {
+            if checkout.trim().is_empty() {
+                return Err(error(format!("flake_checkouts[{}]: must not be empty", i)));
+            }
+        }
+        for (i, root) in file.clone_roots.iter().enumerate() {
+            if root.trim().is_empty() {
+                return Err(error(format!("clone_roots[{}]: must not be empty", i)));
+            }
+        }
+        for (i, rule) in file.rules.iter().enumerate() {
+            rule.validate()
+                .map_err(|msg| error(format!("rules[{}]: {}", i, msg)))?;
+        }
+        for (i, rewrite) in file.rewrites.iter_mut().enumerate() {
+            rewrite
+                .compile()
+                .map_err(|msg| error(format!("rewrites[{}]: {}", i, msg)))?;
+        }
+        for name in file.vars.keys() {
+            validate_var_name(name).map_err(|msg| error(format!("vars: {}", msg)))?;
+        }
+
+        let base = base_dir.to_string_lossy();
+        if file.editor.is_some() {
+            self.editor = file.editor;
+        }
+        if file.system_opener.is_some() {
+            self.system_opener = file.system_opener;
+        }
+        if let Some(strategies) = file.strategies {
+            self.strategies = strategies;
+        }
+        if let Some(normalization) = file.normalization {
+            self.normalization = normalization;
+        }
+        if let Some(case_insensitive) = file.case_insensitive {
+            self.case_insensitive = case_insensitive;
+        }
+        if let Some(did_you_mean) = file.did_you_mean {
+            self.did_you_mean = did_you_mean;
+        }
+        if let Some(cache_dir) = file.cache_dir {
+            self.cache_dir = Some(resolve_path(&cache_dir, &base));
+        }
+        prepend(
+            &mut self.search_roots,
+            file.search_roots
+                .iter()
+                .map(|root| resolve_path(root, &base)),
+        );
+        prepend(&mut self.rules, file.rules);
+        if let Some(directories) = file.directories {
+            self.directories = directories;
+        }
+        prepend(&mut self.rewrites, file.rewrites);
+        if let Some(infer_rewrites) = file.infer_rewrites {
+            self.infer_rewrites = infer_rewrites;
+        }
+        if let Some(map_worktrees) = file.map_worktrees {
+            self.map_worktrees = map_worktrees;
+        }
+        if let Some(expand_vars) = file.expand_vars {
+            self.expand_vars = expand_vars;
+        }
+        self.vars.extend(file.vars);
+        prepend(
+            &mut self.flake_checkouts,
+            file.flake_checkouts
+                .iter()
+                .map(|checkout| resolve_path(checkout, &base)),
+        );
+        if let Some(open_nix_store) = file.open_nix_store {
+            self.open_nix_store = open_nix_store;
+        }
+        if let Some(source_maps) = file.source_maps {
+            self.source_maps = source_maps;
+        }
+        if let Some(sysroot) = file.rust_sysroot {
+            self.rust_sysroot = Some(resolve_path(&sysroot, &base));
+        }
+        prepend(
+            &mut self.clone_roots,
+            file.clone_roots
+                .iter()
+                .map(|root| resolve_path(root, &base)),
+        );
+        if let Some(log) = file.log {
+            if let Some(enabled) = log.enabled {
+                self.log.enabled = enabled;
+            }
+            if let Some(file) = log.file {
+                self.log.file = resolve_path(&file, &base);
+            }
+        }
+
+        self.sources.push(source.to_string());
+        Ok(())
+    }
+
+    /// Apply environment or command line settings on top of the file layers
+    pub fn apply_overrides(
+        &mut self,
+        overrides: &Overrides,
+        source: &str,
+    ) -> Result<(), ConfigError> {
+        if overrides.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(editor) = &overrides.editor {
+            validate_editor(editor).map_err(|message| ConfigError {
+                source: source.to_string(),
+                message: format!("editor: {}", message),
+            })?;
+            self.editor = Some(editor.clone());
+        }
+        if let Some(opener) = &overrides.system_opener {
+            self.system_opener = Some(opener.clone());
+        }
+        if let Some(file) = &overrides.log_file {
+            self.log.file = file.clone();
+        }
+        // The pane passes all of its user vars, and ones that `$NAME` can't reference
+        // are no reason to fail the click
+        for (name, value) in &overrides.vars {
+            if validate_var_name(name).is_ok() {
+                self.vars.insert(name.clone(), value.clone());
+            }
+        }
+
+        self.sources.push(source.to_string());
+        Ok(())
+    }
+
+    /// The opener named by the first rule matching `path`, if any
+    pub fn opener_for(&self, path: &Path) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(path))
+            .map(|rule| rule.opener.as_str())
+    }
+
+    /// Apply the first rewrite whose prefix or regex matches `path`
+    pub fn rewrite(&self, path: &str) -> Option<String> {
+        self.rewrites.iter().find_map(|rewrite| rewrite.apply(path))
+    }
+
+    /// Expand variables in `path` from `vars` and then the environment
+    ///
+    /// Returns None when expansion is disabled or there is nothing to expand.
+    pub fn expand(&self, path: &str) -> Result<Option<String>, String> {
+        if !self.expand_vars || !path.contains('$') {
+            return Ok(None);
+        }
+        let lookup = |name: &str| self.vars.get(name).cloned().or_else(|| env::var(name).ok());
+        expand_vars(path, &lookup).map(Some)
+    }
+
+    /// The effective config as TOML, preceded by a comment listing its sources
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        for source in &self.sources {
+            out.push_str(&format!("# source: {}\n", source));
+        }
+        out.push_str(&toml::to_string_pretty(self).unwrap_or_default());
+        out
+    }
+}
+
+impl OpenerRule {
+    fn validate(&self) -> Result<(), String> {
+        match (&self.glob, &self.extension) {
+            (Some(_), Some(_)) => return Err("set either `glob` or `extension`, not both".into()),
+            (None, None) => return Err("one of `glob` or `extension` is required".into()),
+            (Some(glob), None) => {
+                Pattern::new(glob).map_err(|e| format!("invalid glob '{}': {}", glob, e))?;
+            }
+            (None, Some(ext)) => {
+                if ext.trim_start_matches('.').is_empty() {
+                    return Err("extension must not be empty".into());
+                }
+            }
+        }
+
+        match self.opener.as_str() {
+            "editor" | "system" => Ok(()),
+            command => validate_editor(command).map_err(|msg| format!("opener: {}", msg)),
+        }
+    }
+
+    /// Globs containing a `/` match the whole path, others just the file name
+    pub fn matches(&self, path: &Path) -> bool {
+        if let Some(ext) = &self.extension {
+            let ext = ext.trim_start_matches('.');
+            return path
+                .extension()
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(ext));
+        }
+
+        let Some(pattern) = self.glob.as_deref().and_then(|g| Pattern::new(g).ok()) else {
+            return false;
+        };
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+
+        if pattern.as_str().contains('/') {
+            // Allow "docs/**/*.md" to match anywhere below the path's root
+            let path = path.to_string_lossy();
+            let unanchored = format!("**/{}", pattern.as_str().trim_start_matches('/'));
+            pattern.matches_with(&path, options)
+                || Pattern::new(&unanchored).is_ok_and(|p| p.matches_with(&path, options))
+        } else {
+            path.file_name()
+                .is_some_and(|name| pattern.matches_with(&name.to_string_lossy(), options))
+        }
+    }
+}
+
+impl Rewrite {
+    /// Check the rewrite and compile its regex
+    fn compile(&mut self) -> Result<(), String> {
+        match (&self.from, &self.regex) {
+            (Some(_), Some(_)) => return Err("set either `from` or `regex`, not both".into()),
+            (None, None) => return Err("one of `from` or `regex` is required".into()),
+            (Some(from), None) => {
+                if !from.starts_with('/') {
+                    return Err(format!("`from` must be an absolute path, got '{}'", from));
+                }
+            }
+            (None, Some(regex)) => {
+                let compiled =
+                    Regex::new(regex).map_err(|e| format!("invalid regex '{}': {}", regex, e))?;
+                self.compiled = Some(compiled);
+            }
+        }
+        if self.to.is_empty() {
+            return Err("`to` must not be empty".into());
+        }
+        Ok(())
+    }
+
+    /// Rewrite `path` if it starts with `from` at a component boundary, or replace the
+    /// first match of `regex`
+    pub fn apply(&self, path: &str) -> Option<String> {
+        if self.regex.is_some() {
+            let regex = self.compiled.as_ref()?;
+            return regex
+                .is_match(path)
+                .then(|| regex.replace(path, self.to.as_str()).into_owned());
+        }
+
+        let from = self.from.as_deref()?.trim_end_matches('/');
+        let rest = path.strip_prefix(from)?;
+        if !(rest.is_empty() || rest.starts_with('/')) {
+            return None;
+        }
+        Some(format!("{}{}", self.to.trim_end_matches('/'), rest))
+    }
+}
+
+fn validate_editor(command: &str) -> Result<(), String> {
+    if Editor::from_command(command).is_some() {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' is not a supported editor (expected code, nvim, vim, emacs, emacsclient, \
+             hx, zed, subl, kak, or a JetBrains launcher such as idea)",
+            command
+        ))
+    }
+}
+
+/// Variable names follow the shell's rules so they can be referenced as `$NAME`
+pub fn validate_var_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid variable name", name))
+    }
+}
+
+/// Put `items` in front of `list`, keeping their order
+fn prepend<T>(list: &mut Vec<T>, items: impl IntoIterator<Item = T>) {
+    let mut merged: Vec<T> = items.into_iter().collect();
+    merged.append(list);
+    *list = merged;
+}
+
+/// `$XDG_CONFIG_HOME/wezterm-file-handler/config.toml`, defaulting to `~/.config`
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = env_dir("XDG_CONFIG_HOME", ".config")?;
+    Some(config_home.join("wezterm-file-handler").join("config.toml"))
+}
+
+/// The nearest project config file in `cwd` or one of its ancestors
+pub fn find_project_config(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(contents: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.merge_str(contents, "test.toml", Path::new("/home/user/.config"))?;
+        Ok(config)
+    }
+
+    fn error_message(contents: &str) -> String {
+        merged(contents).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::default();
+        assert_eq!(config.editor, None);
+        assert!(config.log.enabled);
+        assert_eq!(
+            config.log.file,
+            PathBuf::from("/tmp/wezterm-file-handler.log")
+        );
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = merged(
+            r#"
+            editor = "nvim"
+            system_opener = "gio open"
+            search_roots = ["/srv/code", "projects"]
+
+            [[rules]]
+            extension = ".png"
+            opener = "system"
+
+            [[rewrites]]
+            from = "/app"
+            to = "/home/user/app"
+
+            [log]
+            enabled = false
+            file = "handler.log"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.editor.as_deref(), Some("nvim"));
+        assert_eq!(config.system_opener.as_deref(), Some("gio open"));
+        assert_eq!(
+            config.search_roots,
+            [
+                PathBuf::from("/srv/code"),
+                PathBuf::from("/home/user/.config/projects")
+            ]
+        );
+        assert_eq!(config.opener_for(Path::new("/x/logo.PNG")), Some("system"));
+        assert_eq!(
+            config.rewrite("/app/lib/foo.rb").as_deref(),
+            Some("/home/user/app/lib/foo.rb")
+        );
+        assert!(!config.log.enabled);
+        assert_eq!(
+            config.log.file,
+            PathBuf::from("/home/user/.config/handler.log")
+        );
+        assert_eq!(config.sources, ["defaults", "test.toml"]);
+    }
+
+    #[test]
+    fn test_later_layers_take_precedence() {
+        let mut config = Config::default();
+        config
+            .merge_str(
+                "editor = \"code\"\n[[rules]]\nextension = \"md\"\nopener = \"code\"\n",
+                "user",
+                Path::new("/"),
+            )
+            .unwrap();
+        config
+            .merge_str(
+                "[[rules]]\nextension = \"md\"\nopener = \"zed\"\n",
+                "project",
+                Path::new("/"),
+            )
+            .unwrap();
+
+        // Project rule comes first, user editor is kept since the project doesn't set one
+        assert_eq!(config.opener_for(Path::new("README.md")), Some("zed"));
+        assert_eq!(config.editor.as_deref(), Some("code"));
+
+        let cli = Overrides {
+            editor: Some("hx".to_string()),
+            ..Default::default()
+        };
+        config.apply_overrides(&cli, "command line").unwrap();
+        assert_eq!(config.editor.as_deref(), Some("hx"));
+        assert_eq!(
+            config.sources,
+            ["defaults", "user", "project", "command line"]
+        );
+    }
+
+    #[test]
+    fn test_validation_errors_are_precise() {
+        assert_eq!(
+            error_message("editor = \"nano\""),
+            "test.toml: editor: 'nano' is not a supported editor (expected code, nvim, vim, \
+             emacs, emacsclient, hx, zed, subl, kak, or a JetBrains launcher such as idea)"
+        );
+        assert_eq!(
+            error_message("[[rules]]\nglob = \"*.md\"\nextension = \"md\"\nopener = \"code\""),
+            "test.toml: rules[0]: set either `glob` or `extension`, not both"
+        );
+        assert_eq!(
+            error_message("[[rules]]\nopener = \"code\""),
+            "test.toml: rules[0]: one of `glob` or `extension` is required"
+        );
+        assert_eq!(
+            error_message("[[rewrites]]\nfrom = \"app\"\nto = \"/x\""),
+            "test.toml: rewrites[0]: `from` must be an absolute path, got 'app'"
+        );
+        assert!(error_message("[[rules]]\nglob = \"[\"\nopener = \"code\"")
+            .starts_with("test.toml: rules[0]: invalid glob '['"));
+    }
+
+    #[test]
+    fn test_strategies() {
+        assert_eq!(Config::default().strategies, DEFAULT_STRATEGIES);
+
+        let config = merged("strategies = [\"search_roots\", \"cwd\"]").unwrap();
+        assert_eq!(config.strategies, [Strategy::SearchRoots, Strategy::Cwd]);
+
+        assert_eq!(
+            error_message("strategies = []"),
+            "test.toml: strategies: must list at least one strategy"
+        );
+        assert_eq!(
+            error_message("strategies = [\"cwd\", \"cwd\"]"),
+            "test.toml: strategies[1]: 'cwd' is listed more than once"
+        );
+        assert!(error_message("strategies = [\"nearby\"]").contains("unknown variant `nearby`"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(Config::default().normalization, Normalization::Lexical);
+
+        let config = merged("normalization = \"physical\"").unwrap();
+        assert_eq!(config.normalization, Normalization::Physical);
+
+        assert!(error_message("normalization = \"logical\"").contains("unknown variant"));
+    }
+
+    #[test]
+    fn test_vars() {
+        let mut config = Config::default();
+        assert!(config.expand_vars);
+        config
+            .merge_str(
+                "[vars]\nROOT = \"/user\"\nOTHER = \"/other\"\n",
+                "user",
+                Path::new("/"),
+            )
+            .unwrap();
+        config
+            .merge_str("[vars]\nROOT = \"/project\"\n", "project", Path::new("/"))
+            .unwrap();
+        assert_eq!(config.vars["ROOT"], "/project");
+        assert_eq!(config.vars["OTHER"], "/other");
+
+        let pane = Overrides {
+            vars: vec![
+                ("ROOT".to_string(), "/pane".to_string()),
+                ("my-var".to_string(), "x".to_string()),
+            ],
+            ..Default::default()
+        };
+        config.apply_overrides(&pane, "command line").unwrap();
+        assert!(!config.vars.contains_key("my-var"));
+        assert_eq!(
+            config.expand("${ROOT}/x.rs").unwrap().as_deref(),
+            Some("/pane/x.rs")
+        );
+        assert_eq!(config.expand("plain/x.rs").unwrap(), None);
+        assert!(config
+            .expand("$WEZTERM_FILE_HANDLER_UNSET_VAR/x")
+            .unwrap_err()
+            .starts_with("undefined variable 'WEZTERM_FILE_HANDLER_UNSET_VAR'"));
+
+        config.expand_vars = false;
+        assert_eq!(config.expand("${ROOT}/x.rs").unwrap(), None);
+
+        assert_eq!(
+            error_message("[vars]\n\"1X\" = \"a\""),
+            "test.toml: vars: '1X' is not a valid variable name"
+        );
+    }
+
+    #[test]
+    fn test_clone_roots() {
+        let config = merged("clone_roots = [\"~/Code\", \"src\"]").unwrap();
+        assert_eq!(
+            config.clone_roots[1],
+            PathBuf::from("/home/user/.config/src")
+        );
+        assert_eq!(
+            error_message("clone_roots = [\" \"]"),
+            "test.toml: clone_roots[0]: must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_flake_checkouts() {
+        let config = merged("flake_checkouts = [\"~/nix-config\", \"flake\"]").unwrap();
+        assert_eq!(
+            config.flake_checkouts[1],
+            PathBuf::from("/home/user/.config/flake")
+        );
+        assert!(!config.open_nix_store);
+
+        assert!(merged("open_nix_store = true").unwrap().open_nix_store);
+        assert_eq!(
+            error_message("flake_checkouts = [\"\"]"),
+            "test.toml: flake_checkouts[0]: must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let message = error_message("editr = \"code\"");
+        assert!(message.starts_with("test.toml: "), "{}", message);
+        assert!(message.contains("unknown field `editr`"), "{}", message);
+    }
+
+    #[test]
+    fn test_glob_rules() {
+        let rule = |glob: &str| OpenerRule {
+            glob: Some(glob.to_string()),
+            extension: None,
+            opener: "system".to_string(),
+        };
+
+        assert!(rule("*.png").matches(Path::new("/a/b/logo.png")));
+        assert!(!rule("*.png").matches(Path::new("/a/b/logo.jpg")));
+        assert!(rule("docs/**/*.md").matches(Path::new("/repo/docs/guide/intro.md")));
+        assert!(!rule("docs/*.md").matches(Path::new("/repo/docs/guide/intro.md")));
+    }
+
+    #[test]
+    fn test_rewrite_respects_component_boundaries() {
+        let rewrite = Rewrite {
+            from: Some("/app/".to_string()),
+            regex: None,
+            to: "/home/user/app".to_string(),
+            compiled: None,
+        };
+
+        assert_eq!(rewrite.apply("/app").as_deref(), Some("/home/user/app"));
+        assert_eq!(
+            rewrite.apply("/app/x.rb").as_deref(),
+            Some("/home/user/app/x.rb")
+        );
+        assert_eq!(rewrite.apply("/application/x.rb"), None);
+    }
+
+    #[test]
+    fn test_regex_rewrites() {
+        let config = merged(
+            r#"
+            [[rewrites]]
+            regex = "^/Users/([^/]+)/Code/"
+            to = "/home/$1/src/"
+
+            [[rewrites]]
+            regex = '^/builds/(?P<group>[^/]+)/(?P<project>[^/]+)'
+            to = "/home/me/${group}-${project}"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rewrite("/Users/ryanlong/Code/app/x.rs").as_deref(),
+            Some("/home/ryanlong/src/app/x.rs")
+        );
+        assert_eq!(
+            config.rewrite("/builds/org/repo/lib/y.rb").as_deref(),
+            Some("/home/me/org-repo/lib/y.rb")
+        );
+        assert_eq!(config.rewrite("/srv/Users/x/Code/z"), None);
+
+        assert!(error_message("[[rewrites]]\nregex = \"(\"\nto = \"/x\"")
+            .starts_with("test.toml: rewrites[0]: invalid regex '('"));
+        assert_eq!(
+            error_message("[[rewrites]]\nfrom = \"/a\"\nregex = \"a\"\nto = \"/x\""),
+            "test.toml: rewrites[0]: set either `from` or `regex`, not both"
+        );
+        assert_eq!(
+            error_message("[[rewrites]]\nto = \"/x\""),
+            "test.toml: rewrites[0]: one of `from` or `regex` is required"
+        );
+    }
+
+    #[test]
+    fn test_to_toml_lists_sources() {
+        let config = merged("editor = \"code\"").unwrap();
+        let printed = config.to_toml();
+
+        assert!(printed.starts_with("# source: defaults\n# source: test.toml\n"));
+        assert!(printed.contains("editor = \"code\""));
+        assert!(printed.contains("[log]"));
+    }
+}
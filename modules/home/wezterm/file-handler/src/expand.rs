@@ -0,0 +1,160 @@
+use std::env;
+use std::path::PathBuf;
+
+/// The directory named by the environment variable `var`, or `~/<fallback>` when it is
+/// unset or empty, e.g. `$XDG_CACHE_HOME` defaulting to `~/.cache`
+pub fn env_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+}
+
+/// Expand `$NAME`, `${NAME}` and `${NAME:-default}` in `input` like a POSIX shell
+///
+/// `${NAME-default}` uses the default only when NAME is unset, `${NAME:-default}` also
+/// when it is empty, and defaults are expanded in turn. A `$` that doesn't start a
+/// variable name is kept literally. Undefined variables without a default are an error.
+pub fn expand_vars(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("unterminated '${{' in '{}'", input))?;
+            out.push_str(&expand_braced(&braced[..end], input, lookup)?);
+            rest = &braced[end + 1..];
+        } else {
+            let len = name_len(after);
+            if len == 0 {
+                out.push('$');
+                rest = after;
+                continue;
+            }
+            let name = &after[..len];
+            let value = lookup(name)
+                .ok_or_else(|| format!("undefined variable '{}' in '{}'", name, input))?;
+            out.push_str(&value);
+            rest = &after[len..];
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Expand the inside of `${...}`
+fn expand_braced(
+    inner: &str,
+    input: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let len = name_len(inner);
+    let (name, operator) = inner.split_at(len);
+    if name.is_empty() {
+        return Err(format!("bad substitution '${{{}}}' in '{}'", inner, input));
+    }
+
+    let value = lookup(name);
+    let default = if let Some(default) = operator.strip_prefix(":-") {
+        Some((default, value.as_deref().is_none_or(str::is_empty)))
+    } else if let Some(default) = operator.strip_prefix('-') {
+        Some((default, value.is_none()))
+    } else if operator.is_empty() {
+        None
+    } else {
+        return Err(format!("bad substitution '${{{}}}' in '{}'", inner, input));
+    };
+
+    match (value, default) {
+        (_, Some((default, true))) => expand_vars(default, lookup),
+        (Some(value), _) => Ok(value),
+        (None, _) => Err(format!("undefined variable '{}' in '{}'", name, input)),
+    }
+}
+
+/// Length of the shell variable name at the start of `s`
+fn name_len(s: &str) -> usize {
+    let mut chars = s.char_indices();
+    match chars.next() {
+        Some((_, c)) if c == '_' || c.is_ascii_alphabetic() => {}
+        _ => return 0,
+    }
+    chars
+        .find(|(_, c)| !(*c == '_' || c.is_ascii_alphanumeric()))
+        .map(|(i, _)| i)
+        .unwrap_or(s.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Result<String, String> {
+        let lookup = |name: &str| match name {
+            "HOME" => Some("/home/ryan".to_string()),
+            "XDG_CONFIG_HOME" => Some("/home/ryan/.config".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+        expand_vars(input, &lookup)
+    }
+
+    #[test]
+    fn test_plain_and_braced_variables() {
+        assert_eq!(expand("$HOME/.zshrc").unwrap(), "/home/ryan/.zshrc");
+        assert_eq!(expand("${HOME}/.zshrc").unwrap(), "/home/ryan/.zshrc");
+        assert_eq!(
+            expand("$XDG_CONFIG_HOME/app.toml").unwrap(),
+            "/home/ryan/.config/app.toml"
+        );
+        assert_eq!(expand("${HOME}rc").unwrap(), "/home/ryanrc");
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(expand("${ROOT:-/srv}/x.rs").unwrap(), "/srv/x.rs");
+        assert_eq!(
+            expand("${ROOT:-$HOME/code}/x.rs").unwrap(),
+            "/home/ryan/code/x.rs"
+        );
+        assert_eq!(expand("${HOME:-/srv}/x.rs").unwrap(), "/home/ryan/x.rs");
+
+        // ':-' treats empty as unset, '-' does not
+        assert_eq!(expand("${EMPTY:-/srv}/x").unwrap(), "/srv/x");
+        assert_eq!(expand("${EMPTY-/srv}/x").unwrap(), "/x");
+        assert_eq!(expand("${ROOT-/srv}/x").unwrap(), "/srv/x");
+    }
+
+    #[test]
+    fn test_literal_dollars() {
+        assert_eq!(expand("a$/b").unwrap(), "a$/b");
+        assert_eq!(expand("cost$5").unwrap(), "cost$5");
+        assert_eq!(expand("trailing$").unwrap(), "trailing$");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            expand("${PROJECT_ROOT}/src/x.rs"),
+            Err("undefined variable 'PROJECT_ROOT' in '${PROJECT_ROOT}/src/x.rs'".to_string())
+        );
+        assert_eq!(
+            expand("$NOPE/x"),
+            Err("undefined variable 'NOPE' in '$NOPE/x'".to_string())
+        );
+        assert_eq!(
+            expand("${HOME/x"),
+            Err("unterminated '${' in '${HOME/x'".to_string())
+        );
+        assert_eq!(
+            expand("${HOME:=/x}"),
+            Err("bad substitution '${HOME:=/x}' in '${HOME:=/x}'".to_string())
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Web URLs of files in GitHub, GitLab and Gitea (or Forgejo) repositories
+//!
+//! `https://github.com/org/repo/blob/<ref>/app/models/user.rb#L10-L20` names a file in a
+//! repository that is usually cloned locally, so it can open in the editor instead of
+//! the browser. The clone is found in the configured `clone_roots`, either at the
+//! `<root>/<host>/<org>/<repo>` layout or by its remote URLs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::git;
+use crate::FileRef;
+
+/// How deep under a clone root checkouts are looked for, e.g. `github.com/org/repo`
+const MAX_DEPTH: usize = 4;
+
+/// Most directories looked at under one clone root
+const MAX_ENTRIES: usize = 5000;
+
+/// A file or directory in a repository on a forge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeUrl {
+    pub host: String,
+    /// "org/repo", or "group/subgroup/repo" on GitLab
+    pub repo: String,
+    /// The ref and path segments, which can't be told apart when the ref contains '/'
+    segments: Vec<String>,
+    /// The line or range from a `#L10-L20` fragment; the path is empty
+    pub location: FileRef,
+}
+
+impl ForgeUrl {
+    /// Parse GitHub `/<org>/<repo>/blob|tree/<ref>/<path>`, GitLab
+    /// `/<group>/<repo>/-/blob|tree/<ref>/<path>` and Gitea
+    /// `/<org>/<repo>/src/branch|tag|commit/<ref>/<path>` URLs on any host
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?;
+        let (rest, fragment) = rest.split_once('#').unwrap_or((rest, ""));
+        let rest = rest.split_once('?').map_or(rest, |(rest, _)| rest);
+        let (host, path) = rest.split_once('/')?;
+
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let (repo, ref_and_path) = match segments.as_slice() {
+            [org, repo, "blob" | "tree", rest @ ..] => (vec![*org, *repo], rest),
+            [org, repo, "src", "branch" | "tag" | "commit", rest @ ..] => (vec![*org, *repo], rest),
+            _ => {
+                let dash = segments.iter().position(|s| *s == "-")?;
+                match &segments[dash..] {
+                    ["-", "blob" | "tree", rest @ ..] if dash >= 2 => {
+                        (segments[..dash].to_vec(), rest)
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        if ref_and_path.is_empty() {
+            return None;
+        }
+
+        let mut location = FileRef::parse(&format!("#{}", fragment));
+        // A fragment that isn't a line anchor, e.g. a Markdown heading, is ignored
+        if !location.has_location() {
+            location = FileRef::default();
+        }
+        Some(ForgeUrl {
+            host: host.to_lowercase(),
+            repo: repo.join("/"),
+            segments: ref_and_path.iter().map(|s| percent_decode(s)).collect(),
+            location,
+        })
+    }
+
+    /// Each way of splitting the segments into a ref and a path, shortest ref first
+    pub fn ref_splits(&self) -> Vec<(String, String)> {
+        (1..=self.segments.len())
+            .map(|len| {
+                (
+                    self.segments[..len].join("/"),
+                    self.segments[len..].join("/"),
+                )
+            })
+            .collect()
+    }
+
+    /// The local clone of the repository: the cwd's repo, a clone at the
+    /// `<root>/<host>/<repo>` layout, or any checkout under the roots whose remote
+    /// points at the repository
+    pub fn find_clone(&self, roots: &[PathBuf], cwd: &Path) -> Option<PathBuf> {
+        if let Some(root) = git::find_root(cwd) {
+            if self.is_remote_of(&root) {
+                return Some(root);
+            }
+        }
+
+        let name = self.repo.rsplit('/').next().unwrap_or(&self.repo);
+        let laid_out = roots.iter().flat_map(|root| {
+            [
+                root.join(&self.host).join(&self.repo),
+                root.join(&self.repo),
+                root.join(name),
+            ]
+        });
+        for checkout in laid_out {
+            // A clone at the expected place counts unless its remotes say otherwise
+            if checkout.join(".git").exists()
+                && (self.is_remote_of(&checkout) || git::remote_urls(&checkout).is_empty())
+            {
+                return Some(checkout);
+            }
+        }
+
+        roots
+            .iter()
+            .flat_map(|root| checkouts_under(root))
+            .find(|checkout| self.is_remote_of(checkout))
+    }
+
+    /// Whether one of `checkout`'s remotes is this repository
+    fn is_remote_of(&self, checkout: &Path) -> bool {
+        git::remote_urls(checkout).iter().any(|url| {
+            parse_remote(url).is_some_and(|(host, repo)| {
+                host.eq_ignore_ascii_case(&self.host) && repo.eq_ignore_ascii_case(&self.repo)
+            })
+        })
+    }
+}
+
+/// The host and repository path of a remote URL: `https://host/org/repo.git`,
+/// `ssh://git@host:22/org/repo`, or scp-like `git@host:org/repo.git`
+pub fn parse_remote(url: &str) -> Option<(String, String)> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            (host.split(':').next()?, path)
+        }
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some((host.to_lowercase(), path.to_string()))
+}
+
+/// Git checkouts under `root`, breadth first, not looking inside checkouts
+fn checkouts_under(root: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut level = vec![root.to_path_buf()];
+    let mut seen = 0;
+
+    for _ in 0..MAX_DEPTH {
+        let mut next = Vec::new();
+        for dir in level {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut dirs: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| entry.path())
+                .collect();
+            dirs.sort();
+
+            for dir in dirs {
+                seen += 1;
+                if seen > MAX_ENTRIES {
+                    return found;
+                }
+                if dir.join(".git").exists() {
+                    found.push(dir);
+                } else {
+                    next.push(dir);
+                }
+            }
+        }
+        level = next;
+    }
+    found
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::init_repo;
+
+    #[test]
+    fn test_parse_github() {
+        let url =
+            ForgeUrl::parse("https://github.com/org/repo/blob/0a1b2c3/app/models/user.rb#L10-L20")
+                .unwrap();
+        assert_eq!(url.host, "github.com");
+        assert_eq!(url.repo, "org/repo");
+        assert_eq!(
+            url.ref_splits()[0],
+            ("0a1b2c3".into(), "app/models/user.rb".into())
+        );
+        assert_eq!(url.location.line, Some(10));
+        assert_eq!(url.location.end, Some((20, None)));
+
+        let url = ForgeUrl::parse("https://github.com/org/repo/tree/main/app/models").unwrap();
+        assert_eq!(url.ref_splits()[0], ("main".into(), "app/models".into()));
+        assert!(!url.location.has_location());
+
+        let url = ForgeUrl::parse("https://github.com/org/repo/blob/main/My%20Notes.md?plain=1#L3")
+            .unwrap();
+        assert_eq!(url.ref_splits()[0], ("main".into(), "My Notes.md".into()));
+        assert_eq!(url.location.line, Some(3));
+    }
+
+    #[test]
+    fn test_parse_gitlab_and_gitea() {
+        let url = ForgeUrl::parse(
+            "https://gitlab.example.com/group/sub/repo/-/blob/feature/x/lib/a.rb#L5-9",
+        )
+        .unwrap();
+        assert_eq!(url.repo, "group/sub/repo");
+        assert_eq!(
+            url.ref_splits()[..2],
+            [
+                ("feature".into(), "x/lib/a.rb".into()),
+                ("feature/x".into(), "lib/a.rb".into())
+            ]
+        );
+        assert_eq!(url.location.end, Some((9, None)));
+
+        let url =
+            ForgeUrl::parse("https://codeberg.org/org/repo/src/branch/main/src/lib.rs#L7").unwrap();
+        assert_eq!(url.repo, "org/repo");
+        assert_eq!(url.ref_splits()[0], ("main".into(), "src/lib.rs".into()));
+        assert_eq!(url.location.line, Some(7));
+    }
+
+    #[test]
+    fn test_parse_rejects_other_urls() {
+        for url in [
+            "https://github.com/org/repo",
+            "https://github.com/org/repo/pull/12",
+            "https://github.com/org/repo/blob",
+            "https://gitlab.com/-/blob/main/x",
+            "file:///app/x.rb",
+            "app/blob/main/x.rb",
+        ] {
+            assert_eq!(ForgeUrl::parse(url), None, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_parse_remote() {
+        let github = Some(("github.com".to_string(), "org/repo".to_string()));
+        assert_eq!(parse_remote("git@github.com:org/repo.git"), github);
+        assert_eq!(parse_remote("https://github.com/org/repo"), github);
+        assert_eq!(parse_remote("https://GitHub.com/org/repo.git/"), github);
+        assert_eq!(parse_remote("ssh://git@github.com:22/org/repo.git"), github);
+        assert_eq!(
+            parse_remote("git@gitlab.com:group/sub/repo.git"),
+            Some(("gitlab.com".to_string(), "group/sub/repo".to_string()))
+        );
+        assert_eq!(parse_remote("/srv/git/repo.git"), None);
+    }
+
+    #[test]
+    fn test_find_clone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let code = tmp.path().join("Code");
+        let roots = [code.clone()];
+        let url = ForgeUrl::parse("https://github.com/org/repo/blob/main/a.rb").unwrap();
+        assert_eq!(url.find_clone(&roots, tmp.path()), None);
+
+        // Found by the clone's remote wherever it is under the root
+        let elsewhere = code.join("work/repo-fork");
+        init_repo(&elsewhere, &["a.rb"]);
+        git::run(
+            &elsewhere,
+            &["remote", "add", "origin", "git@github.com:org/repo.git"],
+        )
+        .unwrap();
+        assert_eq!(url.find_clone(&roots, tmp.path()), Some(elsewhere.clone()));
+
+        // The host/org/repo layout is checked first
+        let laid_out = code.join("github.com/org/repo");
+        init_repo(&laid_out, &["a.rb"]);
+        assert_eq!(url.find_clone(&roots, tmp.path()), Some(laid_out.clone()));
+
+        // The cwd's repo wins when it is a clone
+        assert_eq!(url.find_clone(&[], &elsewhere), Some(elsewhere.clone()));
+
+        // A different repo at the expected place isn't taken
+        git::run(
+            &laid_out,
+            &["remote", "add", "origin", "git@github.com:other/repo.git"],
+        )
+        .unwrap();
+        assert_eq!(url.find_clone(&roots, tmp.path()), Some(elsewhere));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b%2Fc"), "a b/c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+}
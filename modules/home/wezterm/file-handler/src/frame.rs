@@ -0,0 +1,224 @@
+//! Stack frames from V8/Node and JVM stack traces
+//!
+//! Node frames carry a full path (`at fn (/app/src/x.js:10:5)`), but JVM frames only
+//! name the file (`at com.foo.Bar.baz(Bar.java:42)`). The class's package says which
+//! directory that file is in, so a JVM frame's path becomes `com/foo/Bar.java`, which
+//! the resolver finds under the search roots or anywhere in the repo.
+
+use serde::Serialize;
+
+use crate::FileRef;
+
+/// Which runtime printed a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Runtime {
+    Node,
+    Jvm,
+}
+
+impl Runtime {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Runtime::Node => "node",
+            Runtime::Jvm => "jvm",
+        }
+    }
+}
+
+/// One line of a stack trace that points into a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub runtime: Runtime,
+    /// The function or method, e.g. "fn" or "com.foo.Bar.baz"; None for anonymous frames
+    pub function: Option<String>,
+    /// For JVM frames, the path is the file under its package's directory
+    pub file_ref: FileRef,
+    /// The file name as printed, e.g. "Bar.java" in a JVM frame
+    pub file_name: String,
+}
+
+/// Parse a stack trace line, ignoring indentation and anything after the frame
+///
+/// Frames without a file location (`at <anonymous>`, `at node:internal/...`,
+/// `(Native Method)`) aren't frames we can open and return None.
+pub fn parse(line: &str) -> Option<Frame> {
+    let rest = line.trim().strip_prefix("at ")?;
+    parse_jvm(rest).or_else(|| parse_node(rest))
+}
+
+/// Point `file_ref` at the source file the stack frame in `context` names
+///
+/// For a JVM frame whose file name is `file_ref`'s path, the path gets the package
+/// directories; a missing location is taken from the frame. Returns the frame used.
+pub fn apply(file_ref: &mut FileRef, context: &str) -> Option<Frame> {
+    let frame = parse(context)?;
+    if frame.file_name != file_ref.path && frame.file_ref.path != file_ref.path {
+        return None;
+    }
+
+    file_ref.path = frame.file_ref.path.clone();
+    if !file_ref.has_location() {
+        file_ref.line = frame.file_ref.line;
+        file_ref.column = frame.file_ref.column;
+    }
+    Some(frame)
+}
+
+/// `com.foo.Bar.baz(Bar.java:42)`, optionally with a module or class loader prefix
+/// (`java.base/java.lang.Thread.run(...)`, `app//com.foo.Bar.baz(...)`)
+fn parse_jvm(rest: &str) -> Option<Frame> {
+    let open = rest.find('(')?;
+    let close = open + rest[open..].find(')')?;
+    let method = &rest[..open];
+    if method.is_empty() || method.contains(char::is_whitespace) {
+        return None;
+    }
+    let method = method.rsplit('/').next()?;
+
+    let location = FileRef::parse(&rest[open + 1..close]);
+    if !location.has_location() || location.path.contains('/') {
+        return None;
+    }
+
+    // The class is everything before the method; its package everything before that
+    let (class, _) = method.rsplit_once('.')?;
+    let path = match class.rsplit_once('.') {
+        Some((package, _)) => format!("{}/{}", package.replace('.', "/"), location.path),
+        None => location.path.clone(),
+    };
+
+    Some(Frame {
+        runtime: Runtime::Jvm,
+        function: Some(method.to_string()),
+        file_name: location.path.clone(),
+        file_ref: FileRef { path, ..location },
+    })
+}
+
+/// Extensions of files a bare `at /app/x.js:3:1` frame can point at; other tools print
+/// `at path:line:col` too, e.g. Nix's `at /cfg/x.nix:12:3:`
+const SCRIPT_EXTENSIONS: &[&str] = &["js", "mjs", "cjs", "ts", "tsx", "jsx"];
+
+/// `fn (/app/src/x.js:10:5)`, `async fn (/app/x.js:3:1)` or just `/app/x.js:3:1`, where
+/// the path may be a `file://` URL
+fn parse_node(rest: &str) -> Option<Frame> {
+    let (function, location) = match rest.strip_suffix(')').and_then(|r| r.rsplit_once(" (")) {
+        Some((function, location)) => (Some(function), location),
+        None => (None, rest),
+    };
+    let function = function
+        .map(|function| function.strip_prefix("async ").unwrap_or(function))
+        .map(str::to_string);
+    let location = location.strip_prefix("async ").unwrap_or(location);
+    let location = location.strip_prefix("file://").unwrap_or(location);
+
+    let file_ref = FileRef::parse(location);
+    if !file_ref.has_location()
+        || file_ref.path.starts_with("node:")
+        || file_ref.path.contains(char::is_whitespace)
+    {
+        return None;
+    }
+    let extension = file_ref.path.rsplit_once('.').map(|(_, ext)| ext);
+    if function.is_none() && !extension.is_some_and(|ext| SCRIPT_EXTENSIONS.contains(&ext)) {
+        return None;
+    }
+
+    Some(Frame {
+        runtime: Runtime::Node,
+        function,
+        file_name: file_ref.path.clone(),
+        file_ref,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(path: &str, line: u32, column: Option<u32>) -> FileRef {
+        FileRef {
+            path: path.to_string(),
+            line: Some(line),
+            column,
+            end: None,
+        }
+    }
+
+    #[test]
+    fn test_node_frames() {
+        let frame = parse("    at handler (/app/src/x.js:10:5)").unwrap();
+        assert_eq!(frame.runtime, Runtime::Node);
+        assert_eq!(frame.function.as_deref(), Some("handler"));
+        assert_eq!(frame.file_ref, at("/app/src/x.js", 10, Some(5)));
+
+        let frame = parse("    at async /app/x.js:3:1").unwrap();
+        assert_eq!(frame.function, None);
+        assert_eq!(frame.file_ref, at("/app/x.js", 3, Some(1)));
+
+        let frame = parse("    at async Server.listen (file:///app/server.mjs:7:9)").unwrap();
+        assert_eq!(frame.function.as_deref(), Some("Server.listen"));
+        assert_eq!(frame.file_ref, at("/app/server.mjs", 7, Some(9)));
+
+        let frame = parse("    at Object.<anonymous> (C:/app/x.js:1:2)").unwrap();
+        assert_eq!(frame.function.as_deref(), Some("Object.<anonymous>"));
+        assert_eq!(frame.file_ref, at("C:/app/x.js", 1, Some(2)));
+
+        assert_eq!(
+            parse("    at node:internal/main/run_main_module:28:49"),
+            None
+        );
+        assert_eq!(parse("    at async Promise.all (index 0)"), None);
+        // Only scripts in the bare form, which Nix's error context shares
+        assert_eq!(parse("       at /cfg/x.nix:12:3:"), None);
+        assert!(parse("    at /app/x.ts:3:1").is_some());
+        assert_eq!(parse("    at <anonymous>"), None);
+    }
+
+    #[test]
+    fn test_jvm_frames() {
+        let frame = parse("\tat com.foo.Bar.baz(Bar.java:42)").unwrap();
+        assert_eq!(frame.runtime, Runtime::Jvm);
+        assert_eq!(frame.function.as_deref(), Some("com.foo.Bar.baz"));
+        assert_eq!(frame.file_name, "Bar.java");
+        assert_eq!(frame.file_ref, at("com/foo/Bar.java", 42, None));
+
+        // Nested classes, lambdas and Kotlin files named differently from their class
+        let frame = parse("\tat com.foo.Bar$Inner.lambda$run$0(Bar.java:7)").unwrap();
+        assert_eq!(frame.file_ref.path, "com/foo/Bar.java");
+        let frame = parse("\tat com.foo.UtilKt.main(Util.kt:3)").unwrap();
+        assert_eq!(frame.file_ref.path, "com/foo/Util.kt");
+
+        // Module and class loader prefixes, and logback's packaging suffix
+        let frame = parse("\tat java.base/java.lang.Thread.run(Thread.java:833)").unwrap();
+        assert_eq!(frame.file_ref.path, "java/lang/Thread.java");
+        let frame = parse("\tat app//com.foo.Bar.baz(Bar.java:42) ~[app.jar:?]").unwrap();
+        assert_eq!(frame.file_ref.path, "com/foo/Bar.java");
+
+        // A class in the default package
+        let frame = parse("\tat Main.main(Main.java:5)").unwrap();
+        assert_eq!(frame.file_ref, at("Main.java", 5, None));
+
+        assert_eq!(parse("\tat java.lang.Object.wait(Native Method)"), None);
+        assert_eq!(parse("\tat com.foo.Bar.baz(Unknown Source)"), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let context = "\tat com.foo.Bar.baz(Bar.java:42)";
+        let mut file_ref = FileRef::parse("Bar.java:42");
+        let frame = apply(&mut file_ref, context).unwrap();
+        assert_eq!(frame.runtime, Runtime::Jvm);
+        assert_eq!(file_ref, at("com/foo/Bar.java", 42, None));
+
+        // Only the file the frame names is mapped
+        let mut file_ref = FileRef::parse("Baz.java:42");
+        assert_eq!(apply(&mut file_ref, context), None);
+        assert_eq!(file_ref.path, "Baz.java");
+
+        let mut file_ref = FileRef::new("/app/src/x.js");
+        apply(&mut file_ref, "    at fn (/app/src/x.js:10:5)").unwrap();
+        assert_eq!(file_ref, at("/app/src/x.js", 10, Some(5)));
+    }
+}
@@ -0,0 +1,194 @@
+//! "Did you mean" suggestions for paths that don't exist
+//!
+//! Paths in logs go stale (renamed files), pick up typos, or get cut off at the terminal
+//! width. Starting from the nearest directory that does exist, the rest of the path is
+//! compared against what is actually there by edit distance.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Deepest missing tail that is searched for, e.g. `scr/models/user.rb`
+const MAX_DEPTH: usize = 3;
+
+/// Most directory entries looked at, so a huge tree can't stall the handler
+const MAX_ENTRIES: usize = 5000;
+
+/// What to do with a suggestion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DidYouMean {
+    /// Don't look for similar files
+    Off,
+    /// Report the closest match but open the path as given
+    #[default]
+    Suggest,
+    /// Open the closest match instead when it is a clear winner
+    Open,
+}
+
+/// The closest existing path to a missing one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub path: PathBuf,
+    /// Edits between the missing and suggested path below their common directory
+    pub distance: usize,
+    /// One edit or a cut-off name away, and better than the runner-up, so it can be
+    /// opened without asking
+    pub confident: bool,
+}
+
+/// The existing path most similar to `missing`, if any is close enough
+pub fn suggest(missing: &Path) -> Option<Suggestion> {
+    let base = missing.ancestors().skip(1).find(|dir| dir.is_dir())?;
+    let tail = missing.strip_prefix(base).ok()?;
+    let depth = tail.components().count();
+    if depth == 0 || depth > MAX_DEPTH {
+        return None;
+    }
+    let target = tail.to_string_lossy();
+    let target_len = target.chars().count();
+
+    let mut entries = Vec::new();
+    collect(base, depth, &mut entries);
+    let mut scored: Vec<(usize, PathBuf)> = entries
+        .into_iter()
+        .filter_map(|path| {
+            let relative = path.strip_prefix(base).ok()?.to_string_lossy().into_owned();
+            let distance = score(&target, &relative)?;
+            (distance <= (target_len / 3).max(1)).then_some((distance, path))
+        })
+        .collect();
+    scored.sort();
+
+    let mut scored = scored.into_iter();
+    let (distance, path) = scored.next()?;
+    let runner_up = scored.next().map(|(distance, _)| distance);
+    let confident = distance <= 1 && runner_up.is_none_or(|other| other > distance);
+
+    Some(Suggestion {
+        path,
+        distance,
+        confident,
+    })
+}
+
+/// Distance from `target` to `candidate`, counting a cut-off `target` as one edit
+fn score(target: &str, candidate: &str) -> Option<usize> {
+    if target.chars().count() >= 3 && candidate.starts_with(target) {
+        return Some(1);
+    }
+    Some(edit_distance(target, candidate))
+}
+
+/// Entries exactly `depth` levels below `dir`, skipping hidden directories
+fn collect(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(read) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in read.flatten() {
+        if out.len() >= MAX_ENTRIES {
+            return;
+        }
+        let path = entry.path();
+        if depth == 1 {
+            out.push(path);
+        } else if path.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            collect(&path, depth - 1, out);
+        }
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions and
+/// transpositions of adjacent characters each count as one edit
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("main.rs", "main.rs"), 0);
+        assert_eq!(edit_distance("resolvr.rs", "resolver.rs"), 1);
+        assert_eq!(edit_distance("scr/main.rs", "src/main.rs"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    fn tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_typo_in_file_name() {
+        let dir = tree(&["src/resolver.rs", "src/main.rs"]);
+
+        let found = suggest(&dir.path().join("src/resolvr.rs")).unwrap();
+        assert_eq!(found.path, dir.path().join("src/resolver.rs"));
+        assert_eq!(found.distance, 1);
+        assert!(found.confident);
+    }
+
+    #[test]
+    fn test_typo_in_directory_and_truncation() {
+        let dir = tree(&["src/models/user.rb", "src/models/users_controller.rb"]);
+
+        let found = suggest(&dir.path().join("scr/models/user.rb")).unwrap();
+        assert_eq!(found.path, dir.path().join("src/models/user.rb"));
+        assert!(found.confident);
+
+        let found = suggest(&dir.path().join("src/models/users_contr")).unwrap();
+        assert_eq!(
+            found.path,
+            dir.path().join("src/models/users_controller.rb")
+        );
+        assert!(found.confident);
+    }
+
+    #[test]
+    fn test_ties_and_distant_names_are_not_confident() {
+        let dir = tree(&["a/map.rs", "a/mat.rs", "a/unrelated.txt"]);
+
+        let found = suggest(&dir.path().join("a/max.rs")).unwrap();
+        assert!(!found.confident);
+
+        // Two edits are worth suggesting but not opening
+        let found = suggest(&dir.path().join("a/unreltd.txt")).unwrap();
+        assert_eq!(found.path, dir.path().join("a/unrelated.txt"));
+        assert_eq!(found.distance, 2);
+        assert!(!found.confident);
+
+        assert_eq!(suggest(&dir.path().join("a/completely_different.rs")), None);
+        assert_eq!(suggest(Path::new("/")), None);
+    }
+}
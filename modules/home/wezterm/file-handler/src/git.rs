@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The root of the git checkout containing `dir`: the nearest ancestor with a `.git`
+/// directory, or a `.git` file in the case of worktrees and submodules
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Run git in `dir` and return its stdout, or a message describing the failure
+pub fn run(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The commit HEAD points at, or None in a repo without commits
+pub fn head(root: &Path) -> Option<String> {
+    run(root, &["rev-parse", "HEAD"])
+        .ok()
+        .map(|out| out.trim().to_string())
+}
+
+/// The commit `rev` (a branch, tag or sha) names, or None if the repo doesn't have it
+pub fn resolve_commit(root: &Path, rev: &str) -> Option<String> {
+    let rev = format!("{}^{{commit}}", rev);
+    run(root, &["rev-parse", "--verify", "--quiet", &rev])
+        .ok()
+        .map(|out| out.trim().to_string())
+}
+
+/// The URLs of every remote, e.g. `git@github.com:org/repo.git`
+///
+/// Read straight from the repository's `config` rather than by running git, since
+/// looking for a clone asks this of every checkout under the clone roots. Remotes
+/// defined in included config files aren't seen.
+pub fn remote_urls(root: &Path) -> Vec<String> {
+    let Some(config) = common_dir(root).and_then(|dir| fs::read_to_string(dir.join("config")).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut urls = Vec::new();
+    let mut in_remote = false;
+    for line in config.lines().map(str::trim) {
+        if let Some(section) = line.strip_prefix('[') {
+            // `[remote "origin"]`; the section name is case-insensitive
+            let (name, subsection) = section.split_once(char::is_whitespace).unwrap_or(("", ""));
+            in_remote = name.eq_ignore_ascii_case("remote") && subsection.starts_with('"');
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if in_remote && key.trim().eq_ignore_ascii_case("url") {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            urls.push(value.to_string());
+        }
+    }
+    urls
+}
+
+/// Tracked and untracked-but-not-ignored files, relative to `root`
+pub fn ls_files(root: &Path) -> Result<Vec<String>, String> {
+    let out = run(
+        root,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+    )?;
+    Ok(out
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The repository's shared git directory, read from `.git` metadata: `root/.git` for
+/// the main worktree, or the `commondir` of a linked worktree's admin directory
+pub fn common_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+
+    let admin = read_gitdir(&dot_git)?;
+    let common = match fs::read_to_string(admin.join("commondir")) {
+        Ok(common) => admin.join(common.trim()),
+        Err(_) => admin,
+    };
+    fs::canonicalize(common).ok()
+}
+
+/// Roots of every worktree of the repository containing `root`, main worktree first
+pub fn worktrees(root: &Path) -> Vec<PathBuf> {
+    let Some(common) = common_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut roots = Vec::new();
+    // A bare repository has no main worktree
+    if common.file_name().is_some_and(|name| name == ".git") {
+        roots.extend(common.parent().map(Path::to_path_buf));
+    }
+
+    let mut linked: Vec<PathBuf> = fs::read_dir(common.join("worktrees"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|admin| {
+            let gitdir = fs::read_to_string(admin.path().join("gitdir")).ok()?;
+            let dot_git = admin.path().join(gitdir.trim());
+            Some(dot_git.parent()?.to_path_buf())
+        })
+        .collect();
+    linked.sort();
+    roots.extend(linked);
+
+    roots
+        .into_iter()
+        .map(|root| fs::canonicalize(&root).unwrap_or(root))
+        .collect()
+}
+
+/// Map `path` inside a sibling worktree to the same relative path in the worktree
+/// containing `cwd`
+///
+/// Returns the sibling's root and the mapped path. Nested worktrees (e.g. under
+/// `.worktrees/` in the main checkout) are matched by the longest root.
+pub fn map_to_worktree(path: &Path, cwd: &Path) -> Option<(PathBuf, PathBuf)> {
+    let current = find_root(cwd)?;
+    let current = fs::canonicalize(&current).unwrap_or(current);
+
+    let owner = worktrees(&current)
+        .into_iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())?;
+    if owner == current {
+        return None;
+    }
+
+    let relative = path.strip_prefix(&owner).ok()?;
+    Some((owner.clone(), current.join(relative)))
+}
+
+/// The directory a `.git` file points at with its `gitdir:` line
+fn read_gitdir(dot_git: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(dot_git).ok()?;
+    let gitdir = contents
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))?;
+    Some(dot_git.parent()?.join(gitdir.trim()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// `git init` a repo in `dir` and commit the given files
+    pub(crate) fn init_repo(dir: &Path, files: &[&str]) {
+        fs::create_dir_all(dir).unwrap();
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        for args in [
+            &["init", "-q"][..],
+            &["add", "."],
+            &[
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "commit",
+                "-qm",
+                "init",
+            ],
+        ] {
+            run(dir, args).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_find_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("a/b")).unwrap();
+
+        assert_eq!(find_root(&repo.join("a/b")), Some(repo.clone()));
+        assert_eq!(find_root(&repo), Some(repo.clone()));
+        assert_eq!(find_root(tmp.path()), None);
+    }
+
+    #[test]
+    fn test_find_root_with_git_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().join("worktree");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: /elsewhere/.git/worktrees/wt\n",
+        )
+        .unwrap();
+
+        assert_eq!(find_root(&worktree), Some(worktree.clone()));
+    }
+
+    #[test]
+    fn test_worktrees_and_mapping() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp = fs::canonicalize(tmp.path()).unwrap();
+        let main = tmp.join("main");
+        let feature = tmp.join("feature");
+        let nested = main.join(".worktrees/fix");
+        init_repo(&main, &["src/lib.rs"]);
+        run(&main, &["worktree", "add", "-q", feature.to_str().unwrap()]).unwrap();
+        run(&main, &["worktree", "add", "-q", nested.to_str().unwrap()]).unwrap();
+
+        assert_eq!(common_dir(&feature), Some(main.join(".git")));
+        assert_eq!(
+            worktrees(&feature),
+            [main.clone(), feature.clone(), nested.clone()]
+        );
+
+        // Output from the main checkout clicked in the feature worktree, and vice versa
+        assert_eq!(
+            map_to_worktree(&main.join("src/lib.rs"), &feature.join("src")),
+            Some((main.clone(), feature.join("src/lib.rs")))
+        );
+        assert_eq!(
+            map_to_worktree(&nested.join("src/lib.rs"), &main),
+            Some((nested.clone(), main.join("src/lib.rs")))
+        );
+        assert_eq!(map_to_worktree(&feature.join("src/lib.rs"), &feature), None);
+        assert_eq!(map_to_worktree(Path::new("/etc/hosts"), &feature), None);
+    }
+
+    #[test]
+    fn test_ls_files_and_head() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        init_repo(&repo, &["a.rs", "src/b.rs"]);
+        fs::write(repo.join(".gitignore"), "ignored.log\n").unwrap();
+        fs::write(repo.join("untracked.rs"), "").unwrap();
+        fs::write(repo.join("ignored.log"), "").unwrap();
+
+        let mut files = ls_files(&repo).unwrap();
+        files.sort();
+        assert_eq!(files, [".gitignore", "a.rs", "src/b.rs", "untracked.rs"]);
+
+        assert_eq!(head(&repo).map(|sha| sha.len()), Some(40));
+        assert!(ls_files(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn test_resolve_commit_and_remote_urls() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        init_repo(&repo, &["a.rs"]);
+        assert!(remote_urls(&repo).is_empty());
+
+        let head = head(&repo).unwrap();
+        run(&repo, &["tag", "v1"]).unwrap();
+        assert_eq!(resolve_commit(&repo, "v1").as_deref(), Some(head.as_str()));
+        assert_eq!(resolve_commit(&repo, &head[..7]), Some(head.clone()));
+        assert_eq!(resolve_commit(&repo, "no-such-branch"), None);
+
+        run(
+            &repo,
+            &["remote", "add", "origin", "git@github.com:org/repo.git"],
+        )
+        .unwrap();
+        run(
+            &repo,
+            &["remote", "add", "fork", "https://github.com/me/repo"],
+        )
+        .unwrap();
+        let mut urls = remote_urls(&repo);
+        urls.sort();
+        assert_eq!(
+            urls,
+            ["git@github.com:org/repo.git", "https://github.com/me/repo"]
+        );
+
+        // A linked worktree shares the main checkout's config
+        let worktree = tmp.path().join("wt");
+        run(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "--detach",
+                worktree.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(remote_urls(&worktree).len(), 2);
+    }
+}
@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use crate::expand::env_dir;
+use crate::git;
+
+/// The files in a git repository, for finding a file from a partial path
+///
+/// The list comes from `git ls-files` (tracked plus untracked, minus ignored files) and
+/// is cached on disk, one file per repo root, so repeat clicks don't re-run git. The
+/// cache records the HEAD it was listed at and is only used while HEAD is unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoIndex {
+    pub root: PathBuf,
+    /// Paths relative to `root`
+    pub files: Vec<String>,
+    /// The commit HEAD pointed at when the files were listed
+    head: Option<String>,
+    /// Where the list was cached, if caching is enabled
+    cache_file: Option<PathBuf>,
+    /// Whether `files` came from the cache rather than a fresh `git ls-files`
+    pub from_cache: bool,
+}
+
+/// `$XDG_CACHE_HOME/wezterm-file-handler`, defaulting to `~/.cache`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let cache_home = env_dir("XDG_CACHE_HOME", ".cache")?;
+    Some(cache_home.join("wezterm-file-handler"))
+}
+
+impl RepoIndex {
+    /// Load the index for the repo at `root`, from `cache_dir` when it's up to date
+    pub fn load(root: &Path, cache_dir: Option<&Path>) -> Result<Self, String> {
+        let cache_file = cache_dir.map(|dir| dir.join(cache_key(root)));
+        let head = git::head(root);
+
+        if let Some(contents) = cache_file.as_ref().and_then(|f| fs::read_to_string(f).ok()) {
+            let mut lines = contents.lines();
+            if lines.next() == Some(&head_line(head.as_deref())) {
+                return Ok(RepoIndex {
+                    root: root.to_path_buf(),
+                    files: lines.map(str::to_string).collect(),
+                    head,
+                    cache_file,
+                    from_cache: true,
+                });
+            }
+        }
+
+        let mut index = RepoIndex {
+            root: root.to_path_buf(),
+            files: Vec::new(),
+            head,
+            cache_file,
+            from_cache: false,
+        };
+        index.refresh()?;
+        Ok(index)
+    }
+
+    /// Re-list the repo's files and rewrite the cache
+    pub fn refresh(&mut self) -> Result<(), String> {
+        self.files = git::ls_files(&self.root)?;
+        self.from_cache = false;
+
+        if let Some(cache_file) = &self.cache_file {
+            // A failed cache write only costs speed on the next click
+            let _ = self.write_cache(cache_file);
+        }
+        Ok(())
+    }
+
+    /// Write the cache through a temporary file, so concurrent clicks never read a
+    /// half-written list
+    fn write_cache(&self, cache_file: &Path) -> std::io::Result<()> {
+        if let Some(dir) = cache_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = head_line(self.head.as_deref());
+        for file in &self.files {
+            contents.push('\n');
+            contents.push_str(file);
+        }
+
+        let tmp = cache_file.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, cache_file).inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+    }
+
+    /// Files whose path ends with `suffix` at a component boundary, best match first
+    ///
+    /// Candidates sharing more leading directories with `cwd` rank higher, then
+    /// shallower paths, so `models/user.rb` clicked in `app/` prefers `app/models/user.rb`.
+    pub fn find_suffix(&self, suffix: &str, cwd: &Path) -> Vec<PathBuf> {
+        let suffix = suffix.trim_start_matches("./").trim_end_matches('/');
+        if suffix.is_empty() {
+            return Vec::new();
+        }
+
+        let cwd_components: Vec<&str> = cwd
+            .strip_prefix(&self.root)
+            .map(components)
+            .unwrap_or_default();
+
+        let mut matches: Vec<(usize, usize, &str)> = self
+            .files
+            .iter()
+            .filter(|file| {
+                file.as_str() == suffix
+                    || file
+                        .strip_suffix(suffix)
+                        .is_some_and(|prefix| prefix.ends_with('/'))
+            })
+            .map(|file| {
+                let file_components = components(Path::new(file));
+                let shared = file_components
+                    .iter()
+                    .zip(&cwd_components)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                (shared, file_components.len(), file.as_str())
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(b.2)));
+        matches
+            .into_iter()
+            .map(|(_, _, file)| self.root.join(file))
+            .collect()
+    }
+}
+
+fn components(path: &Path) -> Vec<&str> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Cache file name for a repo root, the same across runs and Rust versions
+///
+/// The hash is 64-bit FNV-1a of the root's path.
+fn cache_key(root: &Path) -> String {
+    let hash = root
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("index-{:016x}", hash)
+}
+
+/// The first line of a cache file, recording the HEAD the files were listed at
+fn head_line(head: Option<&str>) -> String {
+    format!("head {}", head.unwrap_or("none"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::init_repo;
+
+    const FILES: &[&str] = &[
+        "app/models/user.rb",
+        "spec/models/user.rb",
+        "lib/admin/models/user.rb",
+        "app/models/superuser.rb",
+        "README.md",
+    ];
+
+    #[test]
+    fn test_find_suffix_matches_component_boundaries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        init_repo(&root, FILES);
+        let index = RepoIndex::load(&root, None).unwrap();
+
+        let found = index.find_suffix("models/user.rb", &root);
+        assert_eq!(
+            found,
+            [
+                root.join("app/models/user.rb"),
+                root.join("spec/models/user.rb"),
+                root.join("lib/admin/models/user.rb"),
+            ]
+        );
+
+        assert_eq!(index.find_suffix("user.rb", &root).len(), 3);
+        assert_eq!(
+            index.find_suffix("README.md", &root),
+            [root.join("README.md")]
+        );
+        assert!(index.find_suffix("ser.rb", &root).is_empty());
+    }
+
+    #[test]
+    fn test_find_suffix_prefers_files_near_cwd() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        init_repo(&root, FILES);
+        let index = RepoIndex::load(&root, None).unwrap();
+
+        let found = index.find_suffix("models/user.rb", &root.join("spec"));
+        assert_eq!(found[0], root.join("spec/models/user.rb"));
+    }
+
+    #[test]
+    fn test_index_is_cached_until_head_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        let cache = tmp.path().join("cache");
+        init_repo(&root, FILES);
+
+        let first = RepoIndex::load(&root, Some(&cache)).unwrap();
+        assert!(!first.from_cache);
+
+        let second = RepoIndex::load(&root, Some(&cache)).unwrap();
+        assert!(second.from_cache);
+        assert_eq!(second.files, first.files);
+
+        fs::write(root.join("new.rb"), "").unwrap();
+        git::run(&root, &["add", "new.rb"]).unwrap();
+        git::run(
+            &root,
+            &[
+                "-c",
+                "user.name=t",
+                "-c",
+                "user.email=t@t",
+                "commit",
+                "-qm",
+                "new",
+            ],
+        )
+        .unwrap();
+
+        let third = RepoIndex::load(&root, Some(&cache)).unwrap();
+        assert!(!third.from_cache);
+        assert!(third.files.contains(&"new.rb".to_string()));
+
+        // The new listing replaced the old one rather than adding a file
+        let cached: Vec<_> = fs::read_dir(&cache).unwrap().flatten().collect();
+        assert_eq!(cached.len(), 1);
+        assert!(RepoIndex::load(&root, Some(&cache)).unwrap().from_cache);
+    }
+
+    #[test]
+    fn test_cache_key_is_stable() {
+        assert_eq!(cache_key(Path::new("")), "index-cbf29ce484222325");
+        assert_eq!(cache_key(Path::new("/repo")), cache_key(Path::new("/repo")));
+        assert_ne!(
+            cache_key(Path::new("/repo")),
+            cache_key(Path::new("/repo2"))
+        );
+    }
+}
@@ -1,40 +1,98 @@
 use std::env;
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+pub mod cli;
+pub mod config;
+mod expand;
+pub mod forge;
+pub mod frame;
+pub mod fuzzy;
+pub mod git;
+pub mod index;
+mod location;
+pub mod nix;
+pub mod opener;
+mod passwd;
+pub mod plan;
+pub mod prose;
+pub mod resolver;
+pub mod rust;
+pub mod sourcemap;
+
+pub use expand::expand_vars;
+pub use location::FileRef;
 
 /// Extract the file path and optional line:col from a string like "file.rb:123:45"
-pub fn extract_path_and_location(input: &str) -> (String, Option<String>) {
-    // Find the first colon followed by digits
-    if let Some(pos) = input.find(|c: char| c == ':') {
-        let (path, rest) = input.split_at(pos);
-        let rest = &rest[1..]; // skip the colon
-
-        // Check if rest starts with digits (line number)
-        if rest.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-            return (path.to_string(), Some(rest.to_string()));
+pub fn extract_path_and_location(input: &str) -> FileRef {
+    FileRef::parse(input)
+}
+
+/// Expand `~`, `~/path`, `~user` and `~user/path` like the shell does
+///
+/// Returns None for paths that don't start with `~` (or `~` when $HOME is unset), and an
+/// error when `~user` names a user that isn't in the passwd database.
+pub fn expand_home(path: &str) -> Result<Option<PathBuf>, String> {
+    let Some(rest) = path.strip_prefix('~') else {
+        return Ok(None);
+    };
+    let (user, rest) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], rest[pos + 1..].trim_start_matches('/')),
+        None => (rest, ""),
+    };
+
+    let home = if user.is_empty() {
+        match env::var_os("HOME") {
+            Some(home) => PathBuf::from(home),
+            None => return Ok(None),
         }
+    } else {
+        passwd::home_dir(user)?
+    };
+
+    if rest.is_empty() {
+        Ok(Some(home))
+    } else {
+        Ok(Some(home.join(rest)))
     }
+}
 
-    (input.to_string(), None)
+/// How `..` is resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Textually, like `cd -L`: `link/..` is the directory containing `link`
+    #[default]
+    Lexical,
+    /// Through the filesystem, like `realpath`: `link/..` is the parent of the link's target
+    Physical,
 }
 
 /// Resolve a file path relative to a current working directory
+///
+/// A `~user` prefix naming an unknown user is left as a literal relative path; call
+/// [`expand_home`] first to report that as an error.
 pub fn resolve_path(path: &str, cwd: &str) -> PathBuf {
+    resolve_path_with(path, cwd, Normalization::Lexical)
+}
+
+/// [`resolve_path`] with a choice of how `..` components are normalized
+pub fn resolve_path_with(path: &str, cwd: &str, mode: Normalization) -> PathBuf {
     let path_buf = PathBuf::from(path);
 
     // Handle absolute paths
     if path_buf.is_absolute() {
-        return path_buf;
+        return match mode {
+            Normalization::Lexical => path_buf,
+            Normalization::Physical => normalize_path_physical(&path_buf),
+        };
     }
 
     // Handle home directory expansion
-    if path.starts_with("~/") || path == "~" {
-        if let Some(home) = env::var_os("HOME") {
-            let home_path = PathBuf::from(home);
-            if path == "~" {
-                return home_path;
-            }
-            return home_path.join(&path[2..]);
-        }
+    if let Ok(Some(home_path)) = expand_home(path) {
+        return home_path;
     }
 
     // Handle relative paths by joining with cwd
@@ -42,22 +100,39 @@ pub fn resolve_path(path: &str, cwd: &str) -> PathBuf {
     let joined = cwd_path.join(path);
 
     // Normalize the path (resolve . and ..)
-    normalize_path(&joined)
+    normalize_path_with(&joined, mode)
+}
+
+/// Normalize a path in the given mode
+pub fn normalize_path_with(path: &Path, mode: Normalization) -> PathBuf {
+    match mode {
+        Normalization::Lexical => normalize_path(path),
+        Normalization::Physical => normalize_path_physical(path),
+    }
 }
 
 /// Normalize a path by resolving . and .. components
+///
+/// This is purely textual. `..` at the root stays at the root, and leading `..`
+/// components of a relative path are kept since there is nothing to pop.
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = Vec::new();
 
     for component in path.components() {
         match component {
-            std::path::Component::CurDir => {
+            Component::CurDir => {
                 // Skip '.'
             }
-            std::path::Component::ParentDir => {
+            Component::ParentDir => match components.last() {
                 // Go up one level
-                components.pop();
-            }
+                Some(Component::Normal(_)) => {
+                    components.pop();
+                }
+                // Can't go above the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                // Relative path that starts by going up: keep the '..'
+                _ => components.push(component),
+            },
             comp => {
                 components.push(comp);
             }
@@ -67,25 +142,119 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     components.iter().collect()
 }
 
+/// Normalize a path the way the filesystem would, following symlinks before each `..`
+///
+/// The existing part of the result is canonical, like `realpath`; components that
+/// don't exist yet are appended as-is. Relative paths have no filesystem anchor, so
+/// they are normalized lexically.
+pub fn normalize_path_physical(path: &Path) -> PathBuf {
+    if path.is_relative() {
+        return normalize_path(path);
+    }
+
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if let Ok(real) = fs::canonicalize(&resolved) {
+                    resolved = real;
+                }
+                resolved.pop();
+            }
+            comp => resolved.push(comp),
+        }
+    }
+
+    canonicalize_existing(&resolved)
+}
+
+/// Canonicalize the longest existing prefix of `path` and append the rest unchanged
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+
+    loop {
+        if let Ok(real) = fs::canonicalize(existing) {
+            return missing.iter().rev().fold(real, |acc, name| acc.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Find an existing path that matches `path` ignoring case, component by component
+///
+/// At each level an exact-case name is used when it exists; otherwise the first (in
+/// byte order) entry whose name matches case-insensitively. Returns None when some
+/// component has no match. Relative paths are matched against the current directory.
+pub fn find_case_insensitive(path: &Path) -> Option<PathBuf> {
+    let mut found = PathBuf::new();
+
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            found.push(component);
+            continue;
+        };
+
+        let exact = found.join(name);
+        if exact.exists() {
+            found = exact;
+            continue;
+        }
+
+        let dir = if found.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            found.as_path()
+        };
+        let wanted = name.to_string_lossy().to_lowercase();
+        let mut matches: Vec<_> = fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name())
+            .filter(|entry| entry.to_string_lossy().to_lowercase() == wanted)
+            .collect();
+        matches.sort();
+        found.push(matches.into_iter().next()?);
+    }
+
+    found.exists().then_some(found)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn file_ref(path: &str, line: Option<u32>, column: Option<u32>) -> FileRef {
+        FileRef {
+            path: path.to_string(),
+            line,
+            column,
+            end: None,
+        }
+    }
+
     #[test]
     fn test_extract_path_and_location_no_line_numbers() {
         assert_eq!(
             extract_path_and_location("file.rb"),
-            ("file.rb".to_string(), None)
+            file_ref("file.rb", None, None)
         );
 
         assert_eq!(
             extract_path_and_location("./spec/spec_helper.rb"),
-            ("./spec/spec_helper.rb".to_string(), None)
+            file_ref("./spec/spec_helper.rb", None, None)
         );
 
         assert_eq!(
             extract_path_and_location("/absolute/path/file.js"),
-            ("/absolute/path/file.js".to_string(), None)
+            file_ref("/absolute/path/file.js", None, None)
         );
     }
 
@@ -93,37 +262,36 @@ mod tests {
     fn test_extract_path_and_location_with_line_numbers() {
         assert_eq!(
             extract_path_and_location("file.rb:123"),
-            ("file.rb".to_string(), Some("123".to_string()))
+            file_ref("file.rb", Some(123), None)
         );
 
         assert_eq!(
             extract_path_and_location("file.rb:123:45"),
-            ("file.rb".to_string(), Some("123:45".to_string()))
+            file_ref("file.rb", Some(123), Some(45))
         );
 
         assert_eq!(
             extract_path_and_location("./lib/config.rb:40"),
-            ("./lib/config.rb".to_string(), Some("40".to_string()))
+            file_ref("./lib/config.rb", Some(40), None)
         );
 
         assert_eq!(
             extract_path_and_location("/absolute/path/file.rb:10:5"),
-            ("/absolute/path/file.rb".to_string(), Some("10:5".to_string()))
+            file_ref("/absolute/path/file.rb", Some(10), Some(5))
         );
     }
 
     #[test]
     fn test_extract_path_and_location_ruby_stack_traces() {
-        // Ruby stack traces often have :in after the line number
+        // Ruby stack traces often have :in after the line number, which is dropped
         assert_eq!(
             extract_path_and_location("./lib/file.rb:40:in"),
-            ("./lib/file.rb".to_string(), Some("40:in".to_string()))
+            file_ref("./lib/file.rb", Some(40), None)
         );
 
-        // We'll capture :in as part of location - that's fine, 'code' ignores it
         assert_eq!(
             extract_path_and_location("spec/helper.rb:123:in `block'"),
-            ("spec/helper.rb".to_string(), Some("123:in `block'".to_string()))
+            file_ref("spec/helper.rb", Some(123), None)
         );
     }
 
@@ -132,7 +300,7 @@ mod tests {
         // Colons not followed by digits should not be treated as line numbers
         assert_eq!(
             extract_path_and_location("file:scheme"),
-            ("file:scheme".to_string(), None)
+            file_ref("file:scheme", None, None)
         );
     }
 
@@ -222,6 +390,31 @@ mod tests {
         assert!(result.is_absolute());
     }
 
+    #[test]
+    fn test_resolve_path_user_home_directory() {
+        let root_home = passwd::home_dir("root").unwrap();
+
+        assert_eq!(resolve_path("~root", "/tmp"), root_home);
+        assert_eq!(
+            resolve_path("~root/.bashrc", "/tmp"),
+            root_home.join(".bashrc")
+        );
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(expand_home("lib/x.rb"), Ok(None));
+        assert_eq!(expand_home("./~x"), Ok(None));
+        assert_eq!(
+            expand_home("~root/a/b").unwrap(),
+            Some(passwd::home_dir("root").unwrap().join("a/b"))
+        );
+        assert_eq!(
+            expand_home("~no-such-user-wezterm-fh/x"),
+            Err("unknown user 'no-such-user-wezterm-fh'".to_string())
+        );
+    }
+
     #[test]
     fn test_normalize_path_removes_current_dir() {
         assert_eq!(
@@ -253,6 +446,120 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_normalize_path_keeps_leading_parent_dirs_of_relative_paths() {
+        assert_eq!(
+            normalize_path(Path::new("../../a")),
+            PathBuf::from("../../a")
+        );
+        assert_eq!(
+            normalize_path(Path::new("a/../../b")),
+            PathBuf::from("../b")
+        );
+        assert_eq!(
+            normalize_path(Path::new("./../a/./b/..")),
+            PathBuf::from("../a")
+        );
+    }
+
+    #[test]
+    fn test_normalize_path_does_not_pop_past_root() {
+        assert_eq!(normalize_path(Path::new("/../x")), PathBuf::from("/x"));
+        assert_eq!(
+            normalize_path(Path::new("/a/../../../b")),
+            PathBuf::from("/b")
+        );
+        assert_eq!(normalize_path(Path::new("/..")), PathBuf::from("/"));
+    }
+
+    /// `<tmp>/real/project/` with a symlink `<tmp>/link -> real/project`
+    fn symlinked_tree() -> (tempfile::TempDir, PathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        let base = fs::canonicalize(tmp.path()).unwrap();
+        fs::create_dir_all(base.join("real/project/lib")).unwrap();
+        fs::write(base.join("real/sibling.rb"), "").unwrap();
+        std::os::unix::fs::symlink(base.join("real/project"), base.join("link")).unwrap();
+        (tmp, base)
+    }
+
+    #[test]
+    fn test_normalize_path_physical_follows_symlinks_before_parent_dirs() {
+        let (_tmp, base) = symlinked_tree();
+
+        // Lexically, link/.. is base; physically it is the parent of real/project
+        assert_eq!(
+            normalize_path(&base.join("link/../sibling.rb")),
+            base.join("sibling.rb")
+        );
+        assert_eq!(
+            normalize_path_physical(&base.join("link/../sibling.rb")),
+            base.join("real/sibling.rb")
+        );
+    }
+
+    #[test]
+    fn test_normalize_path_physical_canonicalizes_existing_prefix() {
+        let (_tmp, base) = symlinked_tree();
+
+        assert_eq!(
+            normalize_path_physical(&base.join("link/lib/new_file.rb")),
+            base.join("real/project/lib/new_file.rb")
+        );
+        assert_eq!(
+            normalize_path_physical(Path::new("/../..")),
+            PathBuf::from("/")
+        );
+        assert_eq!(
+            normalize_path_physical(Path::new("../a")),
+            PathBuf::from("../a")
+        );
+    }
+
+    #[test]
+    fn test_resolve_path_with_symlinked_cwd() {
+        let (_tmp, base) = symlinked_tree();
+        let cwd = base.join("link");
+        let cwd = cwd.to_str().unwrap();
+
+        assert_eq!(
+            resolve_path_with("../sibling.rb", cwd, Normalization::Lexical),
+            base.join("sibling.rb")
+        );
+        assert_eq!(
+            resolve_path_with("../sibling.rb", cwd, Normalization::Physical),
+            base.join("real/sibling.rb")
+        );
+        assert_eq!(
+            resolve_path_with("./lib", cwd, Normalization::Physical),
+            base.join("real/project/lib")
+        );
+    }
+
+    #[test]
+    fn test_find_case_insensitive_prefers_exact_case() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("app/models")).unwrap();
+        fs::write(root.join("app/models/user.rb"), "").unwrap();
+        fs::create_dir_all(root.join("Docs")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/guide.md"), "").unwrap();
+
+        assert_eq!(
+            find_case_insensitive(&root.join("App/Models/User.rb")),
+            Some(root.join("app/models/user.rb"))
+        );
+        // "docs" exists exactly, so "Docs" isn't considered even though it sorts first
+        assert_eq!(
+            find_case_insensitive(&root.join("docs/GUIDE.md")),
+            Some(root.join("docs/guide.md"))
+        );
+        assert_eq!(
+            find_case_insensitive(&root.join("App/Models/Post.rb")),
+            None
+        );
+    }
+
     #[test]
     fn test_normalize_path_complex() {
         assert_eq!(
@@ -285,8 +592,8 @@ mod tests {
         );
 
         // With line numbers
-        let (path, loc) = extract_path_and_location("./app/models/project_view.rb:41");
-        assert_eq!(resolve_path(&path, cwd), PathBuf::from("/Users/ryanlong/Code/github.com/opencounter/opencounter/app/models/project_view.rb"));
-        assert_eq!(loc, Some("41".to_string()));
+        let file_ref = extract_path_and_location("./app/models/project_view.rb:41");
+        assert_eq!(resolve_path(&file_ref.path, cwd), PathBuf::from("/Users/ryanlong/Code/github.com/opencounter/opencounter/app/models/project_view.rb"));
+        assert_eq!(file_ref.line, Some(41));
     }
 }
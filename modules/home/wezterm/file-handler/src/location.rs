@@ -0,0 +1,297 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// A file path plus an optional position inside that file, as parsed from terminal output
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FileRef {
+    pub path: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// End of a range as (line, column)
+    pub end: Option<(u32, Option<u32>)>,
+}
+
+impl FileRef {
+    /// A reference to a whole file, with no position
+    pub fn new(path: impl Into<String>) -> Self {
+        FileRef {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse strings like "file.rb", "file.rb:123", "file.rb:123:45:in `block'",
+    /// "file.ts(10,5)", "File.cpp(42)", or ranges like "file.rs:10-20",
+    /// "file.rs:10:5-12:8" and "file.rs#L10-L20"
+    ///
+    /// The location starts at the first colon followed by a digit, the first
+    /// parenthesized "(line)" / "(line,col)", or a GitHub-style "#L" anchor, so colons
+    /// inside the path (e.g. `${ROOT:-/srv}`) are kept. Anything after the location
+    /// (":in", a trailing ":", ": message") is discarded.
+    pub fn parse(input: &str) -> Self {
+        let location = input.char_indices().find_map(|(pos, c)| {
+            let rest = &input[pos + 1..];
+            let (start, end) = match c {
+                ':' => colon_location(rest)?,
+                '(' => (paren_location(rest)?, None),
+                '#' => anchor_location(rest)?,
+                _ => return None,
+            };
+            Some((pos, start, end))
+        });
+
+        match location {
+            Some((pos, (line, column), end)) => FileRef {
+                path: input[..pos].to_string(),
+                line: Some(line),
+                column,
+                // A range that ends before it starts is junk, not a selection
+                end: end.filter(|&(end_line, _)| end_line >= line),
+            },
+            None => FileRef::new(input),
+        }
+    }
+
+    /// Whether the reference points somewhere inside the file rather than at the file itself
+    pub fn has_location(&self) -> bool {
+        self.line.is_some()
+    }
+}
+
+/// Formats back to the "path:line:col" (or "path:line:col-line:col") form that
+/// [`FileRef::parse`] reads
+impl fmt::Display for FileRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+            if let Some((line, column)) = self.end {
+                write!(f, "-{}", line)?;
+                if let Some(column) = column {
+                    write!(f, ":{}", column)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A line and optional column
+type Position = (u32, Option<u32>);
+
+/// "12" or "12:5" after a colon, optionally followed by "-20" or "-20:8" to end a
+/// range, ignoring whatever follows
+fn colon_location(s: &str) -> Option<(Position, Option<Position>)> {
+    let (start, rest) = line_and_column(s)?;
+    let end = rest
+        .strip_prefix('-')
+        .and_then(line_and_column)
+        .map(|(end, _)| end);
+    Some((start, end))
+}
+
+/// "12" or "12:5", and what follows
+fn line_and_column(s: &str) -> Option<(Position, &str)> {
+    let (line, rest) = take_number(s)?;
+    match rest.strip_prefix(':').and_then(take_number) {
+        Some((column, rest)) => Some(((line, Some(column)), rest)),
+        None => Some(((line, None), rest)),
+    }
+}
+
+/// A GitHub-style anchor after '#': "L10", "L10-L20" or "L10C5-L12C8", or GitLab's
+/// "L10-20"
+fn anchor_location(s: &str) -> Option<(Position, Option<Position>)> {
+    let (start, rest) = anchor_line(s.strip_prefix('L')?)?;
+    let end = rest
+        .strip_prefix('-')
+        .map(|end| end.strip_prefix('L').unwrap_or(end))
+        .and_then(anchor_line)
+        .map(|(end, _)| end);
+    Some((start, end))
+}
+
+/// "10" or "10C5" after an anchor's 'L', and what follows
+fn anchor_line(s: &str) -> Option<(Position, &str)> {
+    let (line, rest) = take_number(s)?;
+    match rest.strip_prefix('C').and_then(take_number) {
+        Some((column, rest)) => Some(((line, Some(column)), rest)),
+        None => Some(((line, None), rest)),
+    }
+}
+
+/// "12)" or "12,5)" (or "12, 5)") after an opening parenthesis, ending the path: only
+/// a ":" message or whitespace may follow, so "notes(1).txt" is left alone
+fn paren_location(s: &str) -> Option<(u32, Option<u32>)> {
+    let (line, rest) = take_number(s)?;
+    let (column, rest) = match rest.strip_prefix(',') {
+        Some(rest) => {
+            let (column, rest) = take_number(rest.trim_start_matches(' '))?;
+            (Some(column), rest)
+        }
+        None => (None, rest),
+    };
+
+    let after = rest.strip_prefix(')')?;
+    let ends_path = after.is_empty() || after.starts_with(|c: char| c == ':' || c.is_whitespace());
+    ends_path.then_some((line, column))
+}
+
+/// Split a leading run of ASCII digits off `s` and parse it
+pub(crate) fn take_number(s: &str) -> Option<(u32, &str)> {
+    let len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if len == 0 {
+        return None;
+    }
+
+    let number = s[..len].parse().ok()?;
+    Some((number, &s[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(path: &str, line: u32, column: Option<u32>) -> FileRef {
+        FileRef {
+            path: path.to_string(),
+            line: Some(line),
+            column,
+            end: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_plain_path() {
+        assert_eq!(FileRef::parse("src/main.rs"), FileRef::new("src/main.rs"));
+        assert!(!FileRef::parse("src/main.rs").has_location());
+    }
+
+    #[test]
+    fn test_parse_line_and_column() {
+        assert_eq!(FileRef::parse("src/main.rs:7"), at("src/main.rs", 7, None));
+        assert_eq!(
+            FileRef::parse("src/main.rs:7:3"),
+            at("src/main.rs", 7, Some(3))
+        );
+    }
+
+    #[test]
+    fn test_parse_discards_trailing_junk() {
+        assert_eq!(
+            FileRef::parse("./lib/file.rb:40:in"),
+            at("./lib/file.rb", 40, None)
+        );
+        assert_eq!(FileRef::parse("file.rb:40:"), at("file.rb", 40, None));
+        assert_eq!(
+            FileRef::parse("file.rb:40:2: warning: unused variable"),
+            at("file.rb", 40, Some(2))
+        );
+        assert_eq!(
+            FileRef::parse("file.rb:40: syntax error"),
+            at("file.rb", 40, None)
+        );
+        assert_eq!(FileRef::parse("file.rb:40abc"), at("file.rb", 40, None));
+    }
+
+    #[test]
+    fn test_parse_parenthesized_location() {
+        assert_eq!(
+            FileRef::parse("src/app.ts(10,5)"),
+            at("src/app.ts", 10, Some(5))
+        );
+        assert_eq!(
+            FileRef::parse("src/app.ts(10,5): error TS2304: Cannot find name 'x'."),
+            at("src/app.ts", 10, Some(5))
+        );
+        assert_eq!(FileRef::parse("File.cpp(42)"), at("File.cpp", 42, None));
+        assert_eq!(
+            FileRef::parse("Program.cs(7, 13)"),
+            at("Program.cs", 7, Some(13))
+        );
+
+        // Parentheses that aren't a location stay in the path
+        assert_eq!(
+            FileRef::parse("docs/notes(1).txt"),
+            FileRef::new("docs/notes(1).txt")
+        );
+        assert_eq!(
+            FileRef::parse("docs/notes(1).txt:3"),
+            at("docs/notes(1).txt", 3, None)
+        );
+        assert_eq!(FileRef::parse("a(10,)"), FileRef::new("a(10,)"));
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        let range = |path: &str, line, column, end| FileRef {
+            end: Some(end),
+            ..at(path, line, column)
+        };
+
+        assert_eq!(
+            FileRef::parse("file.rs:10-20"),
+            range("file.rs", 10, None, (20, None))
+        );
+        assert_eq!(
+            FileRef::parse("file.rs:10:5-12:8: unused"),
+            range("file.rs", 10, Some(5), (12, Some(8)))
+        );
+        assert_eq!(
+            FileRef::parse("file.rs#L10-L20"),
+            range("file.rs", 10, None, (20, None))
+        );
+        assert_eq!(
+            FileRef::parse("file.rs#L10C5-L12C8"),
+            range("file.rs", 10, Some(5), (12, Some(8)))
+        );
+        assert_eq!(
+            FileRef::parse("file.rs#L10-20"),
+            range("file.rs", 10, None, (20, None))
+        );
+        assert_eq!(FileRef::parse("file.rs#L10"), at("file.rs", 10, None));
+
+        // Backwards ranges and dashes that don't end a range are dropped
+        assert_eq!(FileRef::parse("file.rs:20-10"), at("file.rs", 20, None));
+        assert_eq!(FileRef::parse("file.rs:10-x"), at("file.rs", 10, None));
+        // Anchors that aren't line anchors stay in the path
+        assert_eq!(
+            FileRef::parse("README.md#usage"),
+            FileRef::new("README.md#usage")
+        );
+    }
+
+    #[test]
+    fn test_parse_keeps_colons_in_path() {
+        assert_eq!(
+            FileRef::parse("${ROOT:-/srv}/x.rs:3:1"),
+            at("${ROOT:-/srv}/x.rs", 3, Some(1))
+        );
+        assert_eq!(FileRef::parse("file:scheme"), FileRef::new("file:scheme"));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for input in [
+            "src/main.rs",
+            "src/main.rs:7",
+            "src/main.rs:7:3",
+            "src/main.rs:7-9",
+            "src/main.rs:7:3-9:1",
+        ] {
+            assert_eq!(FileRef::parse(input).to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_parse_line_number_overflow_is_not_a_location() {
+        assert_eq!(
+            FileRef::parse("file.rb:99999999999"),
+            FileRef::new("file.rb:99999999999")
+        );
+    }
+}
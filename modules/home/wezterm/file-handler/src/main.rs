@@ -1,15 +1,26 @@
+use serde::Serialize;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::process::Command;
-use wezterm_file_handler::{extract_path_and_location, resolve_path};
+use std::path::{Path, PathBuf};
+use std::process::Command as ProcessCommand;
+use std::sync::OnceLock;
+use wezterm_file_handler::cli::{Cli, Command, Format, USAGE};
+use wezterm_file_handler::config::{Config, Overrides, DEFAULT_LOG_FILE};
+use wezterm_file_handler::plan::{describe, parse_input, Plan, Resolution};
+
+/// Log destination from the config; None once logging is disabled
+static LOG_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
 
 fn log_to_file(msg: &str) {
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/tmp/wezterm-file-handler.log")
-    {
+    let path = match LOG_FILE.get() {
+        Some(Some(path)) => path.as_path(),
+        Some(None) => return,
+        // Config not loaded yet (or failed to load)
+        None => Path::new(DEFAULT_LOG_FILE),
+    };
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs())
@@ -18,83 +29,181 @@ fn log_to_file(msg: &str) {
     }
 }
 
+/// Output format from the command line, so errors can be reported in it too
+static FORMAT: OnceLock<Format> = OnceLock::new();
+
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => fail(&format!("cannot serialize output: {}", e)),
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    log_to_file(&format!("ERROR: {}", msg));
+    eprintln!("wezterm-file-handler: {}", msg);
+    if FORMAT.get() == Some(&Format::Json) {
+        println!("{}", serde_json::json!({ "error": msg }));
+    }
+    std::process::exit(1);
+}
+
+fn load_config(cwd: &str, overrides: &Overrides) -> Config {
+    match Config::load(Path::new(cwd), overrides) {
+        Ok(config) => {
+            let log_file = config.log.enabled.then(|| config.log.file.clone());
+            let _ = LOG_FILE.set(log_file);
+            config
+        }
+        Err(e) => fail(&format!("invalid configuration: {}", e)),
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
+
+    let cli = match Cli::parse(args.iter().skip(1).cloned()) {
+        Ok(cli) => cli,
+        Err(msg) => {
+            log_to_file(&format!("ERROR: {} (args: {:?})", msg, args));
+            eprintln!("wezterm-file-handler: {}\n\n{}", msg, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        print!("{}", USAGE);
+        return;
+    }
+    let json = cli.format == Format::Json;
+    let _ = FORMAT.set(cli.format);
+
+    let cwd = match &cli.cwd {
+        Some(cwd) => cwd.clone(),
+        None => env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_else(|e| fail(&format!("cannot determine current directory: {}", e))),
+    };
+
+    let config = load_config(&cwd, &cli.overrides);
     log_to_file(&format!("=== Handler invoked with args: {:?}", args));
+    log_to_file(&format!("config sources: {:?}", config.sources));
 
-    if args.len() < 3 {
-        let msg = "Usage: wezterm-file-handler [--dry-run] <path> <cwd>";
-        log_to_file(&format!("ERROR: {}", msg));
-        eprintln!("{}", msg);
-        std::process::exit(1);
+    if cli.command == Command::Config {
+        if json {
+            print_json(&config);
+        } else {
+            print!("{}", config.to_toml());
+        }
+        return;
     }
 
-    let dry_run = args.contains(&"--dry-run".to_string());
-    let offset = if dry_run { 1 } else { 0 };
-    log_to_file(&format!("dry_run={}, offset={}", dry_run, offset));
+    let input = cli.input.as_deref().unwrap_or_default();
+    if cli.command == Command::Parse {
+        let file_ref = parse_input(input, cli.context.as_deref(), &mut Vec::new());
+        if json {
+            print_json(&file_ref);
+        } else {
+            println!("{}", describe(&file_ref));
+        }
+        return;
+    }
 
-    if args.len() < 3 + offset {
-        let msg = "Usage: wezterm-file-handler [--dry-run] <path> <cwd>";
-        log_to_file(&format!("ERROR: {}", msg));
-        eprintln!("{}", msg);
-        std::process::exit(1);
+    let resolution = match Resolution::with_context(input, cli.context.as_deref(), &cwd, &config) {
+        Ok(resolution) => resolution,
+        Err(msg) => fail(&msg),
+    };
+    for step in &resolution.trace {
+        log_to_file(step);
     }
 
-    let path_arg = &args[1 + offset];
-    let cwd_arg = &args[2 + offset];
-    log_to_file(&format!("path_arg='{}', cwd_arg='{}'", path_arg, cwd_arg));
+    if cli.command == Command::Resolve {
+        if json {
+            print_json(&resolution);
+        } else if let Some(url) = &resolution.url {
+            println!("{}", url);
+        } else {
+            println!("{}", resolution.path.display());
+        }
+        return;
+    }
 
-    // Extract the file path without line/col numbers
-    let (file_path, line_col) = extract_path_and_location(path_arg);
-    log_to_file(&format!("extracted: file_path='{}', line_col={:?}", file_path, line_col));
+    if cli.command == Command::Candidates {
+        let candidates = resolution.candidates(Path::new(&cwd));
+        if json {
+            print_json(&serde_json::json!({
+                "input": input,
+                "candidates": candidates,
+            }));
+        } else {
+            for candidate in &candidates {
+                println!("{}", candidate.input);
+            }
+        }
+        return;
+    }
 
-    // Resolve the path relative to cwd
-    let resolved_path = resolve_path(&file_path, cwd_arg);
-    log_to_file(&format!("resolved_path='{}'", resolved_path.display()));
+    let plan = match Plan::new(resolution, &config) {
+        Ok(plan) => plan,
+        Err(msg) => {
+            if cli.command == Command::Explain && !json {
+                println!("error: {}", msg);
+            }
+            fail(&msg)
+        }
+    };
+    log_to_file(
+        plan.resolution
+            .trace
+            .last()
+            .map(String::as_str)
+            .unwrap_or_default(),
+    );
+
+    if json && (cli.command == Command::Explain || cli.dry_run) {
+        log_to_file(&format!("[DRY RUN] Would run: {:?}", plan.argv));
+        print_json(&plan);
+        return;
+    }
 
-    eprintln!("wezterm-file-handler: resolved '{}' to '{}'", path_arg, resolved_path.display());
+    if cli.command == Command::Explain {
+        for step in &plan.resolution.trace {
+            println!("{}", step);
+        }
+        println!("would run: {}", plan.argv.join(" "));
+        return;
+    }
 
-    // Check if file exists
-    if !resolved_path.exists() {
-        log_to_file(&format!("WARNING: File does not exist: {}", resolved_path.display()));
-        eprintln!("wezterm-file-handler: WARNING: File does not exist: {}", resolved_path.display());
+    let argv = &plan.argv;
+    eprintln!(
+        "wezterm-file-handler: resolved '{}' to '{}'",
+        input,
+        plan.resolution.path.display()
+    );
+    for warning in &plan.resolution.warnings {
+        eprintln!("wezterm-file-handler: WARNING: {}", warning);
+    }
+    if !plan.resolution.exists && plan.resolution.url.is_none() {
         // Still try to open - maybe it's a file the user wants to create
-    } else {
-        log_to_file("File exists");
+        eprintln!(
+            "wezterm-file-handler: WARNING: File does not exist: {}",
+            plan.resolution.path.display()
+        );
     }
 
-    // Decide how to open based on whether we have line/col info
-    if dry_run {
+    if cli.dry_run {
         // Dry run mode - just report what would happen
-        if let Some(loc) = line_col {
-            log_to_file(&format!("[DRY RUN] Would run: code --goto {}:{}", resolved_path.display(), loc));
-            eprintln!("wezterm-file-handler: [DRY RUN] Would open with VS Code: {}:{}", resolved_path.display(), loc);
-        } else {
-            log_to_file(&format!("[DRY RUN] Would run: open {}", resolved_path.display()));
-            eprintln!("wezterm-file-handler: [DRY RUN] Would open with system default: {}", resolved_path.display());
-        }
+        log_to_file(&format!("[DRY RUN] Would run: {:?}", argv));
+        eprintln!(
+            "wezterm-file-handler: [DRY RUN] Would run: {}",
+            argv.join(" ")
+        );
         return;
     }
 
-    let status = if let Some(loc) = line_col {
-        // If we have line:col, use VS Code which understands --goto
-        let target = format!("{}:{}", resolved_path.display(), loc);
-        log_to_file(&format!("Executing: /etc/profiles/per-user/ryanlong/bin/code --goto '{}'", target));
-        eprintln!("wezterm-file-handler: Opening with VS Code: {}", target);
-
-        Command::new("/etc/profiles/per-user/ryanlong/bin/code")
-            .arg("--goto")
-            .arg(&target)
-            .status()
-    } else {
-        // No line/col, use macOS 'open' to respect file associations
-        log_to_file(&format!("Executing: open '{}'", resolved_path.display()));
-        eprintln!("wezterm-file-handler: Opening with system default: {}", resolved_path.display());
-
-        Command::new("open")
-            .arg(&resolved_path)
-            .status()
-    };
+    log_to_file(&format!("Executing: {:?}", argv));
+    eprintln!("wezterm-file-handler: Opening: {}", argv.join(" "));
+
+    let status = ProcessCommand::new(&argv[0]).args(&argv[1..]).status();
 
     match status {
         Ok(exit_status) if exit_status.success() => {
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+
+use crate::git;
+
+/// Prefix of every path in the Nix store
+pub const STORE_DIR: &str = "/nix/store/";
+
+/// Length of the hash at the start of a store path name
+const HASH_LEN: usize = 32;
+
+/// The part of a flake source store path below its root, e.g. "modules/x.nix" for
+/// "/nix/store/<hash>-source/modules/x.nix"
+pub fn source_relative(path: &str) -> Option<&str> {
+    let (name, relative) = path.strip_prefix(STORE_DIR)?.split_once('/')?;
+    let (hash, suffix) = name.split_at_checked(HASH_LEN)?;
+
+    let is_source = suffix == "-source" && hash.chars().all(|c| c.is_ascii_alphanumeric());
+    (is_source && !relative.is_empty()).then_some(relative)
+}
+
+/// Flake checkouts to map store paths onto: the configured ones, then the cwd's
+/// repository when it is a flake
+pub fn checkouts(configured: &[PathBuf], cwd: &Path) -> Vec<PathBuf> {
+    let mut checkouts = configured.to_vec();
+    if let Some(root) = git::find_root(cwd).filter(|root| root.join("flake.nix").is_file()) {
+        if !checkouts.contains(&root) {
+            checkouts.push(root);
+        }
+    }
+    checkouts
+}
+
+/// The first checkout that has a file at `relative`
+pub fn find_in_checkouts(relative: &str, checkouts: &[PathBuf]) -> Option<PathBuf> {
+    checkouts
+        .iter()
+        .map(|checkout| checkout.join(relative))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const HASH: &str = "0c2kzyhn4gwmmh1yx5q9bsd7ij8n8dff";
+
+    #[test]
+    fn test_source_relative() {
+        let path = format!(
+            "/nix/store/{}-source/modules/home/wezterm/default.nix",
+            HASH
+        );
+        assert_eq!(
+            source_relative(&path),
+            Some("modules/home/wezterm/default.nix")
+        );
+
+        // Built outputs and other store paths aren't flake sources
+        assert_eq!(
+            source_relative(&format!("/nix/store/{}-bash-5.2/bin/bash", HASH)),
+            None
+        );
+        assert_eq!(source_relative("/nix/store/short-source/x.nix"), None);
+        assert_eq!(
+            source_relative(&format!("/nix/store/{}-source/", HASH)),
+            None
+        );
+        assert_eq!(source_relative("/home/user/source/x.nix"), None);
+    }
+
+    #[test]
+    fn test_checkouts_include_flake_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let flake = dir.path().join("flake");
+        let plain = dir.path().join("plain");
+        git::tests::init_repo(&flake, &["flake.nix", "modules/x.nix"]);
+        git::tests::init_repo(&plain, &["README.md"]);
+        let configured = vec![PathBuf::from("/configured")];
+
+        assert_eq!(
+            checkouts(&configured, &flake.join("modules")),
+            [PathBuf::from("/configured"), flake.clone()]
+        );
+        assert_eq!(checkouts(&configured, &plain), configured);
+    }
+
+    #[test]
+    fn test_find_in_checkouts() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        fs::create_dir_all(first.join("lib")).unwrap();
+        fs::create_dir_all(second.join("modules")).unwrap();
+        fs::write(second.join("modules/x.nix"), "{ }").unwrap();
+
+        let checkouts = [first, second.clone()];
+        assert_eq!(
+            find_in_checkouts("modules/x.nix", &checkouts),
+            Some(second.join("modules/x.nix"))
+        );
+        assert_eq!(find_in_checkouts("modules/y.nix", &checkouts), None);
+    }
+}
@@ -0,0 +1,765 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::FileRef;
+
+/// Something that can open a file, knowing how to express a line/column for its program
+pub trait Opener {
+    /// Short name used in logs and messages
+    fn name(&self) -> &str;
+
+    /// The full argv that opens `path`, positioned at the location in `file_ref` if it has one
+    fn command(&self, path: &Path, file_ref: &FileRef) -> Vec<String>;
+}
+
+/// The editors we know the goto syntax for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorKind {
+    VsCode,
+    Neovim,
+    Vim,
+    Emacs,
+    Helix,
+    Zed,
+    Sublime,
+    JetBrains,
+    Kakoune,
+}
+
+/// Program names searched on PATH when neither $VISUAL nor $EDITOR names a known editor
+const PATH_SEARCH_ORDER: &[&str] = &[
+    "code",
+    "zed",
+    "subl",
+    "nvim",
+    "hx",
+    "emacsclient",
+    "kak",
+    "vim",
+];
+
+impl EditorKind {
+    /// Recognize an editor from its program name, e.g. "nvim" or "/usr/bin/code-insiders"
+    pub fn from_program(program: &str) -> Option<Self> {
+        let name = Path::new(program).file_name()?.to_str()?;
+        let name = name.strip_suffix(".exe").unwrap_or(name);
+
+        let kind = match name {
+            "code" | "code-insiders" | "codium" | "cursor" => EditorKind::VsCode,
+            "nvim" => EditorKind::Neovim,
+            "vim" | "vi" | "gvim" | "mvim" => EditorKind::Vim,
+            "emacs" | "emacsclient" => EditorKind::Emacs,
+            "hx" | "helix" => EditorKind::Helix,
+            "zed" | "zeditor" => EditorKind::Zed,
+            "subl" | "sublime_text" => EditorKind::Sublime,
+            "idea" | "pycharm" | "webstorm" | "rubymine" | "goland" | "clion" | "rider"
+            | "phpstorm" | "rustrover" => EditorKind::JetBrains,
+            "kak" => EditorKind::Kakoune,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorKind::VsCode => "vscode",
+            EditorKind::Neovim => "neovim",
+            EditorKind::Vim => "vim",
+            EditorKind::Emacs => "emacs",
+            EditorKind::Helix => "helix",
+            EditorKind::Zed => "zed",
+            EditorKind::Sublime => "sublime",
+            EditorKind::JetBrains => "jetbrains",
+            EditorKind::Kakoune => "kakoune",
+        }
+    }
+
+    /// Terminal editors need a pane to run in, since WezTerm runs us without a tty
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            EditorKind::Neovim | EditorKind::Vim | EditorKind::Helix | EditorKind::Kakoune
+        )
+    }
+
+    /// Arguments that open `path` at the location in `file_ref`, in this editor's syntax
+    fn goto_args(&self, path: &str, file_ref: &FileRef) -> Vec<String> {
+        let (line, column) = match file_ref.line {
+            Some(line) => (line, file_ref.column),
+            None => return vec![path.to_string()],
+        };
+
+        // "path:line" or "path:line:col", understood by several editors
+        let suffixed = match column {
+            Some(column) => format!("{}:{}:{}", path, line, column),
+            None => format!("{}:{}", path, line),
+        };
+        // "+line" or "+line:col"
+        let plus = match column {
+            Some(column) => format!("+{}:{}", line, column),
+            None => format!("+{}", line),
+        };
+
+        match self {
+            EditorKind::VsCode => vec!["--goto".to_string(), suffixed],
+            EditorKind::Neovim | EditorKind::Vim => {
+                let cmd = match column {
+                    Some(column) => format!("+call cursor({}, {})", line, column),
+                    None => format!("+{}", line),
+                };
+                vec![cmd, path.to_string()]
+            }
+            EditorKind::Emacs | EditorKind::Kakoune => vec![plus, path.to_string()],
+            EditorKind::Helix | EditorKind::Zed | EditorKind::Sublime => vec![suffixed],
+            EditorKind::JetBrains => {
+                let mut args = vec!["--line".to_string(), line.to_string()];
+                if let Some(column) = column {
+                    args.push("--column".to_string());
+                    args.push(column.to_string());
+                }
+                args.push(path.to_string());
+                args
+            }
+        }
+    }
+
+    /// Arguments that open `path` with the range in `file_ref` selected, for editors
+    /// that can select from the command line
+    ///
+    /// Returns None when there is no range or the editor can't select one, in which
+    /// case [`EditorKind::goto_args`] opens it at the start line. That includes VS Code,
+    /// whose `--goto` only takes a position. A range without columns selects whole lines.
+    fn select_args(&self, path: &str, file_ref: &FileRef) -> Option<Vec<String>> {
+        let (line, column) = (file_ref.line?, file_ref.column);
+        let (end_line, end_column) = file_ref.end?;
+
+        match self {
+            EditorKind::Neovim | EditorKind::Vim => {
+                let (mode, column, end_column) = match end_column {
+                    Some(end_column) => ("v", column.unwrap_or(1), end_column),
+                    None => ("V", 1, 1),
+                };
+                let cmd = format!(
+                    "+call cursor({}, {}) | execute \"normal! {}\" | call cursor({}, {})",
+                    line, column, mode, end_line, end_column
+                );
+                Some(vec![cmd, path.to_string()])
+            }
+            EditorKind::Emacs => {
+                // Mark at the end of the range, point at its start
+                let end = match end_column {
+                    Some(end_column) => emacs_goto(end_line, end_column),
+                    None => emacs_goto(end_line + 1, 1),
+                };
+                let expr = format!(
+                    "(progn (find-file {}) {} (push-mark nil t t) {})",
+                    elisp_string(path),
+                    end,
+                    emacs_goto(line, column.unwrap_or(1))
+                );
+                Some(vec!["--eval".to_string(), expr])
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Elisp that moves point to a 1-based line and column
+fn emacs_goto(line: u32, column: u32) -> String {
+    format!(
+        "(goto-char (point-min)) (forward-line {}) (move-to-column {})",
+        line.saturating_sub(1),
+        column.saturating_sub(1)
+    )
+}
+
+/// `s` as an Elisp string literal
+fn elisp_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// An editor program plus any arguments it was configured with (e.g. `$EDITOR="code -r"`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Editor {
+    pub kind: EditorKind,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Editor {
+    /// Build an editor from a shell-style command such as `$VISUAL`
+    ///
+    /// Returns None when the command is empty or the program isn't a known editor.
+    pub fn from_command(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next()?;
+        let kind = EditorKind::from_program(program)?;
+
+        let mut args: Vec<String> = words.map(str::to_string).collect();
+        // emacsclient would otherwise block until the buffer is closed
+        if Path::new(program)
+            .file_name()
+            .is_some_and(|n| n == "emacsclient")
+            && !args.iter().any(|a| a == "-n" || a == "--no-wait")
+        {
+            args.insert(0, "-n".to_string());
+        }
+
+        Some(Editor {
+            kind,
+            program: program.to_string(),
+            args,
+        })
+    }
+
+    /// Use the configured editor if any, else find one via $VISUAL, $EDITOR, then PATH
+    ///
+    /// GUI-launched WezTerm on macOS gets a minimal PATH, so the Nix profile bin
+    /// directories are searched as well.
+    pub fn detect(configured: Option<&str>) -> Option<Self> {
+        if let Some(command) = configured {
+            return Self::from_command(command);
+        }
+
+        let visual = env::var("VISUAL").ok();
+        let editor = env::var("EDITOR").ok();
+
+        let mut dirs: Vec<PathBuf> = env::var_os("PATH")
+            .map(|path| env::split_paths(&path).collect())
+            .unwrap_or_default();
+        if let Some(user) = env::var_os("USER") {
+            dirs.push(Path::new("/etc/profiles/per-user").join(user).join("bin"));
+        }
+        if let Some(home) = env::var_os("HOME") {
+            dirs.push(Path::new(&home).join(".nix-profile/bin"));
+        }
+        let path = env::join_paths(dirs).unwrap_or_default();
+
+        Self::detect_from(visual.as_deref(), editor.as_deref(), |program| {
+            find_in_path(program, &path)
+        })
+    }
+
+    /// Detection logic behind [`Editor::detect`], with the environment passed in
+    pub fn detect_from(
+        visual: Option<&str>,
+        editor: Option<&str>,
+        find_program: impl Fn(&str) -> Option<PathBuf>,
+    ) -> Option<Self> {
+        if let Some(found) = [visual, editor]
+            .into_iter()
+            .flatten()
+            .find_map(Editor::from_command)
+        {
+            return Some(found);
+        }
+
+        PATH_SEARCH_ORDER.iter().find_map(|program| {
+            let path = find_program(program)?;
+            Editor::from_command(path.to_str()?)
+        })
+    }
+}
+
+impl Opener for Editor {
+    fn name(&self) -> &str {
+        self.kind.name()
+    }
+
+    fn command(&self, path: &Path, file_ref: &FileRef) -> Vec<String> {
+        let mut argv = Vec::new();
+        if self.kind.is_terminal() {
+            argv.extend(["wezterm", "cli", "spawn", "--"].map(String::from));
+        }
+
+        argv.push(self.program.clone());
+        argv.extend(self.args.iter().cloned());
+        let path = path.to_string_lossy();
+        match self.kind.select_args(&path, file_ref) {
+            Some(args) => argv.extend(args),
+            None => argv.extend(self.kind.goto_args(&path, file_ref)),
+        }
+        argv
+    }
+}
+
+/// The platform's "open with default application" command, used when there is no location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemOpener {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+/// Environment variable that overrides the system opener, e.g. "gio open" or "thunar"
+pub const SYSTEM_OPENER_ENV: &str = "WEZTERM_FILE_HANDLER_OPENER";
+
+impl SystemOpener {
+    /// Build an opener from a shell-style command such as "gio open"
+    pub fn from_command(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next()?;
+
+        Some(SystemOpener {
+            program: program.to_string(),
+            args: words.map(str::to_string).collect(),
+        })
+    }
+
+    /// Use the configured opener if any, otherwise the platform's opener
+    pub fn detect(configured: Option<&str>) -> Result<Self, String> {
+        if let Some(command) = configured {
+            return Self::from_command(command)
+                .ok_or_else(|| "The configured system opener is empty".to_string());
+        }
+
+        let path = env::var_os("PATH").unwrap_or_default();
+        Self::detect_for(env::consts::OS, |program| find_in_path(program, &path))
+    }
+
+    /// Pick the opener for `os` (as in `std::env::consts::OS`) among programs that exist
+    pub fn detect_for(
+        os: &str,
+        find_program: impl Fn(&str) -> Option<PathBuf>,
+    ) -> Result<Self, String> {
+        let candidates: &[&[&str]] = match os {
+            "macos" => &[&["open"]],
+            "linux" | "freebsd" | "openbsd" | "netbsd" | "dragonfly" => {
+                &[&["xdg-open"], &["gio", "open"]]
+            }
+            _ => return Err(format!("No system opener known for platform '{}'", os)),
+        };
+
+        candidates
+            .iter()
+            .find_map(|command| {
+                let program = find_program(command[0])?;
+                Some(SystemOpener {
+                    program: program.to_str()?.to_string(),
+                    args: command[1..].iter().map(|arg| arg.to_string()).collect(),
+                })
+            })
+            .ok_or_else(|| {
+                let tried: Vec<String> = candidates.iter().map(|c| c.join(" ")).collect();
+                format!(
+                    "No system opener found on PATH (tried: {}); set `system_opener` in \
+                     the config or {} to override",
+                    tried.join(", "),
+                    SYSTEM_OPENER_ENV
+                )
+            })
+    }
+}
+
+impl Opener for SystemOpener {
+    fn name(&self) -> &str {
+        "system"
+    }
+
+    fn command(&self, path: &Path, _file_ref: &FileRef) -> Vec<String> {
+        let mut argv = vec![self.program.clone()];
+        argv.extend(self.args.iter().cloned());
+        argv.push(path.to_string_lossy().into_owned());
+        argv
+    }
+}
+
+/// What to do with a path that resolves to a directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryAction {
+    /// Show it in the file manager with the system opener
+    #[default]
+    Reveal,
+    /// Open it as a workspace/project in the editor
+    Workspace,
+    /// Spawn a new WezTerm tab in it
+    Tab,
+    /// Split the current WezTerm pane with a new one in it
+    Pane,
+}
+
+impl DirectoryAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DirectoryAction::Reveal => "reveal",
+            DirectoryAction::Workspace => "workspace",
+            DirectoryAction::Tab => "tab",
+            DirectoryAction::Pane => "pane",
+        }
+    }
+}
+
+/// Starts a shell in a directory through `wezterm cli`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeztermSpawn {
+    Tab,
+    Pane,
+}
+
+impl Opener for WeztermSpawn {
+    fn name(&self) -> &str {
+        match self {
+            WeztermSpawn::Tab => "wezterm tab",
+            WeztermSpawn::Pane => "wezterm pane",
+        }
+    }
+
+    fn command(&self, path: &Path, _file_ref: &FileRef) -> Vec<String> {
+        let subcommand = match self {
+            WeztermSpawn::Tab => "spawn",
+            WeztermSpawn::Pane => "split-pane",
+        };
+        vec![
+            "wezterm".to_string(),
+            "cli".to_string(),
+            subcommand.to_string(),
+            "--cwd".to_string(),
+            path.to_string_lossy().into_owned(),
+        ]
+    }
+}
+
+/// Pick the opener for `path`: the configured directory action for directories, then
+/// the first matching config rule, otherwise the editor when there is a location to
+/// jump to and the system opener when there isn't
+pub fn select_opener(
+    config: &Config,
+    path: &Path,
+    file_ref: &FileRef,
+) -> Result<Box<dyn Opener>, String> {
+    if path.is_dir() {
+        return match config.directories {
+            DirectoryAction::Reveal => select_named(config, "system"),
+            DirectoryAction::Workspace => select_named(config, "editor"),
+            DirectoryAction::Tab => Ok(Box::new(WeztermSpawn::Tab)),
+            DirectoryAction::Pane => Ok(Box::new(WeztermSpawn::Pane)),
+        };
+    }
+
+    let default = if file_ref.has_location() {
+        "editor"
+    } else {
+        "system"
+    };
+    select_named(config, config.opener_for(path).unwrap_or(default))
+}
+
+/// The opener for "system", "editor" or an editor command
+fn select_named(config: &Config, name: &str) -> Result<Box<dyn Opener>, String> {
+    match name {
+        "system" => Ok(Box::new(SystemOpener::detect(
+            config.system_opener.as_deref(),
+        )?)),
+        "editor" => match Editor::detect(config.editor.as_deref()) {
+            Some(editor) => Ok(Box::new(editor)),
+            None => Err(
+                "No editor found: set `editor` in the config, $VISUAL or $EDITOR, \
+                         or install one on PATH"
+                    .to_string(),
+            ),
+        },
+        command => match Editor::from_command(command) {
+            Some(editor) => Ok(Box::new(editor)),
+            None => Err(format!("'{}' is not a supported editor", command)),
+        },
+    }
+}
+
+/// Look up an executable by name in a PATH-style list of directories
+pub fn find_in_path(program: &str, path_var: &std::ffi::OsStr) -> Option<PathBuf> {
+    env::split_paths(path_var)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(command: &str, file_ref: &FileRef) -> Vec<String> {
+        let editor = Editor::from_command(command).unwrap();
+        editor.command(Path::new("/src/main.rs"), file_ref)
+    }
+
+    fn at(line: u32, column: Option<u32>) -> FileRef {
+        FileRef {
+            path: "main.rs".to_string(),
+            line: Some(line),
+            column,
+            end: None,
+        }
+    }
+
+    #[test]
+    fn test_from_program_recognizes_editors() {
+        assert_eq!(EditorKind::from_program("code"), Some(EditorKind::VsCode));
+        assert_eq!(
+            EditorKind::from_program("/etc/profiles/per-user/ryanlong/bin/code"),
+            Some(EditorKind::VsCode)
+        );
+        assert_eq!(EditorKind::from_program("nvim"), Some(EditorKind::Neovim));
+        assert_eq!(
+            EditorKind::from_program("emacsclient"),
+            Some(EditorKind::Emacs)
+        );
+        assert_eq!(
+            EditorKind::from_program("rubymine"),
+            Some(EditorKind::JetBrains)
+        );
+        assert_eq!(EditorKind::from_program("nano"), None);
+    }
+
+    #[test]
+    fn test_goto_syntax_per_editor() {
+        let loc = at(10, Some(5));
+
+        assert_eq!(argv("code", &loc), ["code", "--goto", "/src/main.rs:10:5"]);
+        assert_eq!(
+            argv("nvim", &loc),
+            [
+                "wezterm",
+                "cli",
+                "spawn",
+                "--",
+                "nvim",
+                "+call cursor(10, 5)",
+                "/src/main.rs"
+            ]
+        );
+        assert_eq!(
+            argv("emacsclient", &loc),
+            ["emacsclient", "-n", "+10:5", "/src/main.rs"]
+        );
+        assert_eq!(
+            argv("hx", &loc),
+            ["wezterm", "cli", "spawn", "--", "hx", "/src/main.rs:10:5"]
+        );
+        assert_eq!(argv("zed", &loc), ["zed", "/src/main.rs:10:5"]);
+        assert_eq!(argv("subl", &loc), ["subl", "/src/main.rs:10:5"]);
+        assert_eq!(
+            argv("idea", &loc),
+            ["idea", "--line", "10", "--column", "5", "/src/main.rs"]
+        );
+        assert_eq!(
+            argv("kak", &loc),
+            [
+                "wezterm",
+                "cli",
+                "spawn",
+                "--",
+                "kak",
+                "+10:5",
+                "/src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_goto_syntax_line_only() {
+        let loc = at(10, None);
+
+        assert_eq!(argv("code", &loc), ["code", "--goto", "/src/main.rs:10"]);
+        assert_eq!(
+            argv("vim", &loc),
+            [
+                "wezterm",
+                "cli",
+                "spawn",
+                "--",
+                "vim",
+                "+10",
+                "/src/main.rs"
+            ]
+        );
+        assert_eq!(argv("idea", &loc), ["idea", "--line", "10", "/src/main.rs"]);
+    }
+
+    #[test]
+    fn test_ranges_are_selected_where_supported() {
+        let range = |column, end| FileRef {
+            end: Some(end),
+            ..at(10, column)
+        };
+        let chars = range(Some(5), (12, Some(8)));
+        let lines = range(None, (20, None));
+
+        assert_eq!(
+            argv("nvim", &chars)[5],
+            "+call cursor(10, 5) | execute \"normal! v\" | call cursor(12, 8)"
+        );
+        assert_eq!(
+            argv("vim", &lines)[5],
+            "+call cursor(10, 1) | execute \"normal! V\" | call cursor(20, 1)"
+        );
+        assert_eq!(
+            argv("emacsclient", &lines),
+            [
+                "emacsclient",
+                "-n",
+                "--eval",
+                "(progn (find-file \"/src/main.rs\") \
+                 (goto-char (point-min)) (forward-line 20) (move-to-column 0) \
+                 (push-mark nil t t) \
+                 (goto-char (point-min)) (forward-line 9) (move-to-column 0))"
+            ]
+        );
+
+        // Editors without a command-line selection open at the start
+        assert_eq!(
+            argv("code", &chars),
+            ["code", "--goto", "/src/main.rs:10:5"]
+        );
+        assert_eq!(argv("zed", &chars), ["zed", "/src/main.rs:10:5"]);
+        assert_eq!(
+            argv("idea", &lines),
+            ["idea", "--line", "10", "/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_elisp_escaping() {
+        assert_eq!(elisp_string(r#"/a "b"\c"#), r#""/a \"b\"\\c""#);
+    }
+
+    #[test]
+    fn test_no_location_passes_just_the_path() {
+        let whole_file = FileRef::new("main.rs");
+
+        assert_eq!(argv("code", &whole_file), ["code", "/src/main.rs"]);
+        assert_eq!(argv("zed", &whole_file), ["zed", "/src/main.rs"]);
+    }
+
+    #[test]
+    fn test_from_command_keeps_extra_args() {
+        assert_eq!(
+            argv("code -r", &at(3, None)),
+            ["code", "-r", "--goto", "/src/main.rs:3"]
+        );
+        assert_eq!(
+            argv("emacsclient --no-wait", &at(3, None)),
+            ["emacsclient", "--no-wait", "+3", "/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_detect_prefers_visual_then_editor_then_path() {
+        let nothing_on_path = |_: &str| None;
+
+        let found = Editor::detect_from(Some("zed"), Some("nvim"), nothing_on_path).unwrap();
+        assert_eq!(found.kind, EditorKind::Zed);
+
+        // Unknown $VISUAL falls through to $EDITOR
+        let found = Editor::detect_from(Some("nano"), Some("nvim"), nothing_on_path).unwrap();
+        assert_eq!(found.kind, EditorKind::Neovim);
+
+        let on_path = |program: &str| (program == "hx").then(|| PathBuf::from("/usr/bin/hx"));
+        let found = Editor::detect_from(None, None, on_path).unwrap();
+        assert_eq!(found.kind, EditorKind::Helix);
+        assert_eq!(found.program, "/usr/bin/hx");
+
+        assert_eq!(Editor::detect_from(None, None, nothing_on_path), None);
+    }
+
+    #[test]
+    fn test_system_opener_per_platform() {
+        let everything = |program: &str| Some(PathBuf::from("/usr/bin").join(program));
+
+        let opener = SystemOpener::detect_for("macos", everything).unwrap();
+        assert_eq!(opener.program, "/usr/bin/open");
+
+        let opener = SystemOpener::detect_for("linux", everything).unwrap();
+        assert_eq!(opener.program, "/usr/bin/xdg-open");
+        assert!(opener.args.is_empty());
+    }
+
+    #[test]
+    fn test_system_opener_falls_back_to_gio() {
+        let only_gio = |program: &str| (program == "gio").then(|| PathBuf::from("/usr/bin/gio"));
+
+        let opener = SystemOpener::detect_for("linux", only_gio).unwrap();
+        assert_eq!(
+            opener.command(Path::new("/tmp/a.png"), &FileRef::new("a.png")),
+            ["/usr/bin/gio", "open", "/tmp/a.png"]
+        );
+    }
+
+    #[test]
+    fn test_system_opener_errors_when_missing() {
+        let err = SystemOpener::detect_for("linux", |_| None).unwrap_err();
+        assert!(err.contains("xdg-open, gio open"), "{}", err);
+        assert!(err.contains(SYSTEM_OPENER_ENV), "{}", err);
+
+        assert!(SystemOpener::detect_for("windows", |_| None).is_err());
+    }
+
+    #[test]
+    fn test_select_opener_uses_rules_then_location() {
+        let mut config = Config {
+            editor: Some("zed".to_string()),
+            system_opener: Some("xdg-open".to_string()),
+            ..Default::default()
+        };
+        config
+            .merge_str(
+                "[[rules]]\nextension = \"png\"\nopener = \"system\"\n\n\
+                 [[rules]]\nextension = \"md\"\nopener = \"editor\"\n",
+                "test",
+                Path::new("/"),
+            )
+            .unwrap();
+
+        let name = |path: &str, file_ref: &FileRef| {
+            select_opener(&config, Path::new(path), file_ref)
+                .unwrap()
+                .name()
+                .to_string()
+        };
+
+        assert_eq!(name("/a/main.rs", &at(1, None)), "zed");
+        assert_eq!(name("/a/main.rs", &FileRef::new("main.rs")), "system");
+        assert_eq!(name("/a/logo.png", &at(1, None)), "system");
+        assert_eq!(name("/a/README.md", &FileRef::new("README.md")), "zed");
+    }
+
+    #[test]
+    fn test_select_opener_for_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            editor: Some("code".to_string()),
+            system_opener: Some("xdg-open".to_string()),
+            ..Default::default()
+        };
+        let open = |config: &Config| {
+            let opener = select_opener(config, dir.path(), &at(3, None)).unwrap();
+            (
+                opener.name().to_string(),
+                opener
+                    .command(dir.path(), &FileRef::new("x"))
+                    .into_iter()
+                    .take(3)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+        };
+
+        assert_eq!(open(&config).0, "system");
+        config.directories = DirectoryAction::Workspace;
+        assert_eq!(open(&config).0, "vscode");
+        config.directories = DirectoryAction::Tab;
+        assert_eq!(
+            open(&config),
+            ("wezterm tab".to_string(), "wezterm cli spawn".to_string())
+        );
+        config.directories = DirectoryAction::Pane;
+        assert_eq!(
+            open(&config),
+            (
+                "wezterm pane".to_string(),
+                "wezterm cli split-pane".to_string()
+            )
+        );
+    }
+}
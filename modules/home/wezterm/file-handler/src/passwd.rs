@@ -0,0 +1,69 @@
+use std::ffi::{CStr, CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::ptr;
+
+/// Look up a user's home directory in the passwd database, as the shell does for `~user`
+pub fn home_dir(user: &str) -> Result<PathBuf, String> {
+    let name = CString::new(user).map_err(|_| format!("invalid user name '{}'", user))?;
+
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    // SAFETY: passwd is a plain C struct for which all-zero is a valid value
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = ptr::null_mut();
+
+    loop {
+        // SAFETY: every pointer refers to a live buffer of the length we pass
+        let rc = unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                &mut entry,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            )
+        };
+        match rc {
+            0 => break,
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            errno => {
+                return Err(format!(
+                    "cannot look up user '{}': {}",
+                    user,
+                    std::io::Error::from_raw_os_error(errno)
+                ))
+            }
+        }
+    }
+
+    if result.is_null() || entry.pw_dir.is_null() {
+        return Err(format!("unknown user '{}'", user));
+    }
+
+    // SAFETY: getpwnam_r succeeded, so pw_dir points to a NUL-terminated string in buf
+    let dir = unsafe { CStr::from_ptr(entry.pw_dir) };
+    Ok(PathBuf::from(OsStr::from_bytes(dir.to_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_dir_of_root() {
+        let home = home_dir("root").unwrap();
+        assert!(home.is_absolute(), "{}", home.display());
+    }
+
+    #[test]
+    fn test_unknown_user() {
+        assert_eq!(
+            home_dir("no-such-user-wezterm-fh"),
+            Err("unknown user 'no-such-user-wezterm-fh'".to_string())
+        );
+        assert_eq!(
+            home_dir("a\0b"),
+            Err("invalid user name 'a\0b'".to_string())
+        );
+    }
+}
@@ -0,0 +1,578 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::forge::ForgeUrl;
+use crate::frame;
+use crate::fuzzy::{self, DidYouMean};
+use crate::git;
+use crate::nix;
+use crate::opener::{select_opener, Opener, SystemOpener};
+use crate::prose;
+use crate::resolver::{self, Strategy};
+use crate::rust;
+use crate::sourcemap::{self, SourceMap};
+use crate::{expand_home, extract_path_and_location, FileRef};
+
+/// Where an input from the terminal points, and how we got there
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Resolution {
+    pub input: String,
+    pub file_ref: FileRef,
+    pub path: PathBuf,
+    pub exists: bool,
+    /// The strategy that found an existing file
+    pub strategy: Option<Strategy>,
+    /// Other existing files the input could refer to, best first
+    pub alternatives: Vec<PathBuf>,
+    /// The closest existing file when the path doesn't exist and wasn't substituted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<PathBuf>,
+    /// Human-readable record of each decision, for `explain` and the log
+    pub trace: Vec<String>,
+    /// Things the user should know even though the path resolved, e.g. a version mismatch
+    pub warnings: Vec<String>,
+    /// A web URL to open in the browser instead, when it has no local clone to open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// A resolution plus the command that would open it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Plan {
+    #[serde(flatten)]
+    pub resolution: Resolution,
+    pub opener: String,
+    pub argv: Vec<String>,
+}
+
+/// The steps taken while resolving, for `explain`, and the warnings among them
+#[derive(Debug, Default)]
+struct Notes {
+    trace: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Notes {
+    fn push(&mut self, step: String) {
+        self.trace.push(step);
+    }
+
+    /// Record something the user should hear about; it goes in the trace too
+    fn warn(&mut self, message: String) {
+        self.trace.push(format!("warning: {}", message));
+        self.warnings.push(message);
+    }
+}
+
+impl Resolution {
+    /// Parse `input` and resolve it against `cwd` with the config's rewrites and strategies
+    ///
+    /// Fails when the path can't mean anything, e.g. `~user` for an unknown user or an
+    /// undefined `$VAR`.
+    pub fn new(input: &str, cwd: &str, config: &Config) -> Result<Self, String> {
+        Self::with_context(input, None, cwd, config)
+    }
+
+    /// Like [`Resolution::new`], also reading `context`, the line of text the input was
+    /// found in: a JVM stack frame there gives a bare file name its package directory,
+    /// and a location written in words (e.g. Python's `File "x.py", line 42`) its line
+    pub fn with_context(
+        input: &str,
+        context: Option<&str>,
+        cwd: &str,
+        config: &Config,
+    ) -> Result<Self, String> {
+        if let Some(url) = ForgeUrl::parse(input) {
+            return Ok(Self::from_forge_url(input, &url, Path::new(cwd), config));
+        }
+        if input.starts_with("https://") || input.starts_with("http://") {
+            let mut notes = Notes::default();
+            notes.push(format!(
+                "'{}' is not a forge file URL; opening it in the browser",
+                input
+            ));
+            return Ok(Self::in_browser(input, notes));
+        }
+
+        let mut notes = Notes::default();
+
+        let mut file_ref = parse_input(input, context, &mut notes.trace);
+
+        if let Some(expanded) = config.expand(&file_ref.path)? {
+            notes.push(format!("expanded '{}' to '{}'", file_ref.path, expanded));
+            file_ref.path = expanded;
+        }
+
+        let rewritten = config.rewrite(&file_ref.path);
+        if let Some(rewritten) = &rewritten {
+            notes.push(format!("rewrote '{}' to '{}'", file_ref.path, rewritten));
+            file_ref.path = rewritten.clone();
+        }
+
+        if let Some(relative) = nix::source_relative(&file_ref.path) {
+            if config.open_nix_store {
+                notes.push("open_nix_store is set; keeping the store path".to_string());
+            } else {
+                let checkouts = nix::checkouts(&config.flake_checkouts, Path::new(cwd));
+                match nix::find_in_checkouts(relative, &checkouts) {
+                    Some(local) => {
+                        notes.push(format!(
+                            "mapped store path '{}' to {}",
+                            file_ref.path,
+                            local.display()
+                        ));
+                        file_ref.path = local.to_string_lossy().into_owned();
+                    }
+                    None => notes.push(format!(
+                        "no flake checkout has '{}'; keeping the store path",
+                        relative
+                    )),
+                }
+            }
+        }
+
+        if let Some(local) = map_rust_path(&file_ref.path, config, &mut notes) {
+            file_ref.path = local.to_string_lossy().into_owned();
+        }
+
+        if let Some(home) = expand_home(&file_ref.path)? {
+            notes.push(format!(
+                "expanded '{}' to {}",
+                file_ref.path,
+                home.display()
+            ));
+        }
+
+        if config.map_worktrees && file_ref.path.starts_with('/') {
+            let path = Path::new(&file_ref.path);
+            if let Some((worktree, local)) = git::map_to_worktree(path, Path::new(cwd)) {
+                if local.exists() {
+                    notes.push(format!(
+                        "mapped '{}' from worktree {} to {}",
+                        file_ref.path,
+                        worktree.display(),
+                        local.display()
+                    ));
+                    file_ref.path = local.to_string_lossy().into_owned();
+                } else {
+                    notes.push(format!(
+                        "{} is not in this worktree; keeping the path in {}",
+                        local.display(),
+                        worktree.display()
+                    ));
+                }
+            }
+        }
+
+        if config.infer_rewrites && rewritten.is_none() {
+            if let Some((prefix, local)) =
+                resolver::infer_rewrite(Path::new(&file_ref.path), Path::new(cwd))
+            {
+                notes.push(format!(
+                    "{} does not exist; inferred rewrite of '{}' to {}",
+                    file_ref.path,
+                    prefix.display(),
+                    local.display()
+                ));
+                file_ref.path = local.to_string_lossy().into_owned();
+            }
+        }
+
+        let mut found = resolver::resolve(&file_ref.path, Path::new(cwd), config);
+        notes.trace.append(&mut found.trace);
+        let mut path = found.path;
+
+        let mut exists = found.strategy.is_some();
+        let mut suggestion = None;
+        if !exists && config.did_you_mean != DidYouMean::Off {
+            match fuzzy::suggest(&path) {
+                Some(found) if found.confident && config.did_you_mean == DidYouMean::Open => {
+                    let message = format!(
+                        "{} does not exist; opening {} instead",
+                        path.display(),
+                        found.path.display()
+                    );
+                    notes.warn(message);
+                    path = found.path;
+                    exists = true;
+                }
+                Some(found) => {
+                    notes.push(format!("did you mean {}?", found.path.display()));
+                    suggestion = Some(found.path);
+                }
+                None => {}
+            }
+        }
+
+        if exists && config.source_maps {
+            if let Some(original) = map_to_source(&path, &file_ref, &mut notes.trace) {
+                path = original.path;
+                file_ref.path = path.to_string_lossy().into_owned();
+                file_ref.line = Some(original.line);
+                file_ref.column = Some(original.column);
+                file_ref.end = None;
+                // Other generated files' locations don't carry over to the original
+                found.alternatives.clear();
+            }
+        }
+        if !exists {
+            notes.push(format!(
+                "{} does not exist; opening it anyway",
+                path.display()
+            ));
+        }
+
+        Ok(Resolution {
+            input: input.to_string(),
+            file_ref,
+            path,
+            exists,
+            strategy: found.strategy,
+            alternatives: found.alternatives,
+            suggestion,
+            trace: notes.trace,
+            warnings: notes.warnings,
+            url: None,
+        })
+    }
+
+    /// Open a GitHub/GitLab/Gitea file URL in the local clone, or in the browser when
+    /// there is no clone or the file isn't in it
+    fn from_forge_url(input: &str, url: &ForgeUrl, cwd: &Path, config: &Config) -> Self {
+        let mut notes = Notes::default();
+        notes.push(format!(
+            "parsed '{}' as a file in {}/{}",
+            input, url.host, url.repo
+        ));
+
+        let Some(clone) = url.find_clone(&config.clone_roots, cwd) else {
+            notes.push(format!(
+                "no local clone of {}/{}; opening the URL in the browser",
+                url.host, url.repo
+            ));
+            return Self::in_browser(input, notes);
+        };
+        notes.push(format!("found the local clone at {}", clone.display()));
+
+        // A ref with '/' in it can't be told from the path, so take the split whose ref
+        // the clone knows
+        let splits = url.ref_splits();
+        let known = splits.iter().find_map(|(git_ref, path)| {
+            let commit = git::resolve_commit(&clone, git_ref)
+                .or_else(|| git::resolve_commit(&clone, &format!("origin/{}", git_ref)))?;
+            Some((git_ref, path, commit))
+        });
+        let path = match known {
+            Some((git_ref, path, commit)) => {
+                match git::head(&clone) {
+                    Some(head) if head != commit => notes.warn(format!(
+                        "{} is at {} but the URL is for '{}' ({}); lines may not match",
+                        clone.display(),
+                        &head[..head.len().min(9)],
+                        git_ref,
+                        &commit[..commit.len().min(9)]
+                    )),
+                    _ => {}
+                }
+                path
+            }
+            None => {
+                let (git_ref, path) = &splits[0];
+                notes.warn(format!(
+                    "'{}' is not in {}; fetch it for matching lines",
+                    git_ref,
+                    clone.display()
+                ));
+                path
+            }
+        };
+
+        let local = clone.join(path);
+        if !local.exists() {
+            let message = format!(
+                "{} does not exist in the local clone; opening the URL in the browser",
+                local.display()
+            );
+            notes.warn(message);
+            return Self::in_browser(input, notes);
+        }
+        notes.push(format!("mapped the URL to {}", local.display()));
+
+        Resolution {
+            input: input.to_string(),
+            file_ref: FileRef {
+                path: local.to_string_lossy().into_owned(),
+                ..url.location.clone()
+            },
+            path: local,
+            exists: true,
+            strategy: None,
+            alternatives: Vec::new(),
+            suggestion: None,
+            trace: notes.trace,
+            warnings: notes.warnings,
+            url: None,
+        }
+    }
+
+    /// A web URL that opens in the browser rather than as a file
+    fn in_browser(input: &str, notes: Notes) -> Self {
+        Resolution {
+            input: input.to_string(),
+            file_ref: FileRef::new(input),
+            path: PathBuf::from(input),
+            exists: false,
+            strategy: None,
+            alternatives: Vec::new(),
+            suggestion: None,
+            trace: notes.trace,
+            warnings: notes.warnings,
+            url: Some(input.to_string()),
+        }
+    }
+
+    /// Every existing file the input could refer to, best first, for a picker
+    pub fn candidates(&self, cwd: &Path) -> Vec<Candidate> {
+        let paths = self.exists.then_some(&self.path);
+        let base = git::find_root(cwd).unwrap_or_else(|| cwd.to_path_buf());
+
+        paths
+            .into_iter()
+            .chain(&self.alternatives)
+            .map(|path| {
+                let at = |path: &Path| FileRef {
+                    path: path.to_string_lossy().into_owned(),
+                    ..self.file_ref.clone()
+                };
+                Candidate {
+                    path: path.clone(),
+                    label: at(path.strip_prefix(&base).unwrap_or(path)).to_string(),
+                    input: at(path).to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Map standard library paths from backtraces to the toolchain's `rust-src`, and
+/// another machine's cargo registry sources to the local registry
+fn map_rust_path(path: &str, config: &Config, notes: &mut Notes) -> Option<PathBuf> {
+    if let Some((hash, relative)) = rust::std_relative(path) {
+        let sysroot = match rust::sysroot(config.rust_sysroot.as_deref()) {
+            Ok(sysroot) => sysroot,
+            Err(e) => {
+                notes.warn(format!("cannot map '{}' to rust-src: {}", path, e));
+                return None;
+            }
+        };
+        let src = rust::rust_src(&sysroot);
+        if !src.is_dir() {
+            notes.warn(format!(
+                "{} does not exist; install it with `rustup component add rust-src`",
+                src.display()
+            ));
+        }
+        match rust::commit_hash(&sysroot) {
+            Some(installed) if installed != hash => notes.warn(format!(
+                "'{}' is from rustc {} but the toolchain at {} is {}; lines may not match",
+                path,
+                &hash[..9],
+                sysroot.display(),
+                &installed[..installed.len().min(9)]
+            )),
+            _ => {}
+        }
+
+        let local = src.join(relative);
+        notes.push(format!(
+            "mapped rustc path '{}' to {}",
+            path,
+            local.display()
+        ));
+        return Some(local);
+    }
+
+    let relative = rust::registry_relative(path)?;
+    if Path::new(path).exists() {
+        return None;
+    }
+    let local = rust::local_registry_src()?.join(relative);
+    if !local.exists() {
+        notes.push(format!("{} is not in the local cargo registry", relative));
+        return None;
+    }
+    notes.push(format!(
+        "mapped cargo registry path '{}' to {}",
+        path,
+        local.display()
+    ));
+    Some(local)
+}
+
+/// The original source for a location in generated JavaScript, when its source map
+/// has a mapping there and the source exists
+fn map_to_source(
+    generated: &Path,
+    file_ref: &FileRef,
+    trace: &mut Vec<String>,
+) -> Option<sourcemap::Original> {
+    let extension = generated.extension()?.to_str()?;
+    if !sourcemap::GENERATED_EXTENSIONS.contains(&extension) {
+        return None;
+    }
+    let line = file_ref.line?;
+
+    let map = match SourceMap::for_generated(generated) {
+        Ok(Some(map)) => map,
+        Ok(None) => return None,
+        Err(e) => {
+            trace.push(format!("source map for {}: {}", generated.display(), e));
+            return None;
+        }
+    };
+    let Some(original) = map.lookup(line, file_ref.column) else {
+        trace.push(format!(
+            "source map for {} has no mapping at line {}; opening the generated file",
+            generated.display(),
+            line
+        ));
+        return None;
+    };
+    if !original.path.exists() {
+        trace.push(format!(
+            "source map points at {} which does not exist; opening the generated file",
+            original.path.display()
+        ));
+        return None;
+    }
+
+    trace.push(format!(
+        "source map: {} line {} is {} line {} column {}",
+        generated.display(),
+        line,
+        original.path.display(),
+        original.line,
+        original.column
+    ));
+    Some(original)
+}
+
+/// One choice offered when an input is ambiguous
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub path: PathBuf,
+    /// Short form for display, relative to the repo root when there is one
+    pub label: String,
+    /// What to pass back to `open` to open this candidate at the input's location
+    pub input: String,
+}
+
+impl Plan {
+    /// Choose an opener for a resolved path and build its command
+    pub fn new(mut resolution: Resolution, config: &Config) -> Result<Self, String> {
+        if let Some(url) = &resolution.url {
+            let opener = SystemOpener::detect(config.system_opener.as_deref())?;
+            let argv = opener.command(Path::new(url), &resolution.file_ref);
+            resolution.trace.push(format!(
+                "chose opener '{}' (URL has no local file)",
+                opener.name()
+            ));
+            return Ok(Plan {
+                resolution,
+                opener: opener.name().to_string(),
+                argv,
+            });
+        }
+
+        let opener = select_opener(config, &resolution.path, &resolution.file_ref)?;
+        let is_dir = resolution.path.is_dir();
+        let argv = if is_dir {
+            // A line and column mean nothing for a directory
+            let whole = FileRef::new(resolution.file_ref.path.clone());
+            opener.command(&resolution.path, &whole)
+        } else {
+            opener.command(&resolution.path, &resolution.file_ref)
+        };
+
+        let reason = match config.opener_for(&resolution.path) {
+            _ if is_dir => format!(
+                "path is a directory; directories = '{}'",
+                config.directories.name()
+            ),
+            Some(rule) => format!("config rule '{}'", rule),
+            None if resolution.file_ref.has_location() => "input has a location".to_string(),
+            None => "input has no location".to_string(),
+        };
+        resolution
+            .trace
+            .push(format!("chose opener '{}' ({})", opener.name(), reason));
+
+        Ok(Plan {
+            resolution,
+            opener: opener.name().to_string(),
+            argv,
+        })
+    }
+}
+
+/// Parse `input`, which may be a whole stack frame, and fill in what `context` (the
+/// line of text it was found in) adds: package directories from a JVM frame, or a
+/// location written in words
+pub fn parse_input(input: &str, context: Option<&str>, trace: &mut Vec<String>) -> FileRef {
+    let mut file_ref = match frame::parse(input) {
+        Some(frame) => {
+            trace.push(format!(
+                "parsed '{}' as a {} stack frame: {}",
+                input,
+                frame.runtime.name(),
+                describe(&frame.file_ref)
+            ));
+            frame.file_ref
+        }
+        None => {
+            let file_ref = extract_path_and_location(input);
+            trace.push(format!("parsed '{}' as {}", input, describe(&file_ref)));
+            file_ref
+        }
+    };
+    let Some(context) = context else {
+        return file_ref;
+    };
+
+    if let Some(frame) = frame::apply(&mut file_ref, context) {
+        trace.push(format!(
+            "found a {} stack frame for {} in the surrounding text: {}",
+            frame.runtime.name(),
+            frame.function.as_deref().unwrap_or("an anonymous function"),
+            describe(&file_ref)
+        ));
+    }
+    if let Some(parser) = prose::apply(&mut file_ref, context) {
+        trace.push(format!(
+            "read the location from the surrounding text ({}): {}",
+            parser,
+            describe(&file_ref)
+        ));
+    }
+    file_ref
+}
+
+/// One-line description of a parsed reference, e.g. "path 'a.rs' line 3 column 7"
+pub fn describe(file_ref: &FileRef) -> String {
+    let mut out = format!("path '{}'", file_ref.path);
+    if let Some(line) = file_ref.line {
+        out.push_str(&format!(" line {}", line));
+    }
+    if let Some(column) = file_ref.column {
+        out.push_str(&format!(" column {}", column));
+    }
+    if let Some((line, column)) = file_ref.end {
+        out.push_str(&format!(" to line {}", line));
+        if let Some(column) = column {
+            out.push_str(&format!(" column {}", column));
+        }
+    }
+    out
+}
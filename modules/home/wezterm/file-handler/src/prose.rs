@@ -0,0 +1,178 @@
+//! Locations written in words next to the path rather than as `path:line:col`
+//!
+//! The hyperlink only covers the path, so these parsers look at the text of the line
+//! it was clicked on. Each one knows one ecosystem's phrasing and gets the text before
+//! and after the path.
+
+use regex::Regex;
+
+use crate::location::take_number;
+use crate::FileRef;
+
+/// A line and optional column
+type Location = (u32, Option<u32>);
+
+/// One ecosystem's way of writing a location
+struct Parser {
+    name: &'static str,
+    parse: fn(before: &str, after: &str) -> Option<Location>,
+}
+
+/// Tried in order; the generic parser comes last since it accepts the loosest text
+const PARSERS: &[Parser] = &[
+    Parser {
+        name: "python",
+        parse: python,
+    },
+    Parser {
+        name: "terraform",
+        parse: terraform,
+    },
+    Parser {
+        name: "nix",
+        parse: nix,
+    },
+    Parser {
+        name: "generic",
+        parse: generic,
+    },
+];
+
+/// Fill in the line and column of `file_ref` from the line of text it appeared in
+///
+/// Returns the name of the parser that found them. A reference that already has a
+/// location, or whose path isn't in `context`, is left alone.
+pub fn apply(file_ref: &mut FileRef, context: &str) -> Option<&'static str> {
+    if file_ref.has_location() || file_ref.path.is_empty() {
+        return None;
+    }
+    let pos = context.find(&file_ref.path)?;
+    let (before, after) = (&context[..pos], &context[pos + file_ref.path.len()..]);
+
+    PARSERS.iter().find_map(|parser| {
+        let (line, column) = (parser.parse)(before, after)?;
+        file_ref.line = Some(line);
+        file_ref.column = column;
+        Some(parser.name)
+    })
+}
+
+/// `File "app/x.py", line 42, in foo`
+fn python(before: &str, after: &str) -> Option<Location> {
+    if !before.trim_end().ends_with("File \"") {
+        return None;
+    }
+    let line = after.strip_prefix("\", line ")?;
+    Some((take_number(line)?.0, None))
+}
+
+/// `on main.tf line 12, in resource "aws_instance" "web":`
+fn terraform(before: &str, after: &str) -> Option<Location> {
+    if !(before.ends_with("on ") || before.ends_with("in ")) {
+        return None;
+    }
+    let line = after.strip_prefix(" line ")?;
+    Some((take_number(line)?.0, None))
+}
+
+/// `at /path/x.nix:12:3:` when the hyperlink stopped short of the location, or the
+/// quoted `at '/path/x.nix:12:3'` of older releases
+fn nix(before: &str, after: &str) -> Option<Location> {
+    if !(before.ends_with("at ") || before.ends_with("at '")) {
+        return None;
+    }
+    let (line, rest) = take_number(after.strip_prefix(':')?)?;
+    let column = rest
+        .strip_prefix(':')
+        .and_then(take_number)
+        .map(|(column, _)| column);
+    Some((line, column))
+}
+
+/// `x.js: line 3, col 7, Missing semicolon.`, `x.json: expected value at line 3 column 7`
+fn generic(_before: &str, after: &str) -> Option<Location> {
+    let pattern = Regex::new(r"(?i)\bline\s+(\d+)(?:\s*,?\s*(?:column|col)\s+(\d+))?").ok()?;
+    let captures = pattern.captures(after)?;
+    let line = captures[1].parse().ok()?;
+    let column = captures.get(2).and_then(|m| m.as_str().parse().ok());
+    Some((line, column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn located(path: &str, context: &str) -> Option<(&'static str, u32, Option<u32>)> {
+        let mut file_ref = FileRef::new(path);
+        let parser = apply(&mut file_ref, context)?;
+        Some((parser, file_ref.line?, file_ref.column))
+    }
+
+    #[test]
+    fn test_python() {
+        assert_eq!(
+            located("app/x.py", r#"  File "app/x.py", line 42, in foo"#),
+            Some(("python", 42, None))
+        );
+        assert_eq!(python("", r#"", line 42"#), None);
+    }
+
+    #[test]
+    fn test_terraform() {
+        assert_eq!(
+            located(
+                "main.tf",
+                r#"  on main.tf line 12, in resource "aws_instance" "web":"#
+            ),
+            Some(("terraform", 12, None))
+        );
+        assert_eq!(terraform("on ", " lines 12"), None);
+    }
+
+    #[test]
+    fn test_nix() {
+        assert_eq!(
+            located("/cfg/x.nix", "       at /cfg/x.nix:12:3:"),
+            Some(("nix", 12, Some(3)))
+        );
+        assert_eq!(
+            located(
+                "/cfg/x.nix",
+                "error: undefined variable 'x' at '/cfg/x.nix:7'"
+            ),
+            Some(("nix", 7, None))
+        );
+        assert_eq!(nix("at ", "/x"), None);
+    }
+
+    #[test]
+    fn test_generic() {
+        assert_eq!(
+            located("src/x.js", "src/x.js: line 3, col 7, Missing semicolon."),
+            Some(("generic", 3, Some(7)))
+        );
+        assert_eq!(
+            located(
+                "config/app.json",
+                "config/app.json: expected value at line 3 column 7"
+            ),
+            Some(("generic", 3, Some(7)))
+        );
+        assert_eq!(
+            located("run.sh", "In run.sh Line 9:"),
+            Some(("generic", 9, None))
+        );
+        assert_eq!(generic("", ": baseline 9"), None);
+    }
+
+    #[test]
+    fn test_existing_location_and_missing_path_are_left_alone() {
+        let mut file_ref = FileRef::parse("x.py:3");
+        assert_eq!(apply(&mut file_ref, r#"File "x.py", line 42"#), None);
+        assert_eq!(file_ref.line, Some(3));
+
+        let mut file_ref = FileRef::new("y.py");
+        assert_eq!(apply(&mut file_ref, r#"File "x.py", line 42"#), None);
+        assert_eq!(file_ref.line, None);
+    }
+}
@@ -0,0 +1,410 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::index::RepoIndex;
+use crate::{find_case_insensitive, git, normalize_path_with, resolve_path_with};
+
+/// One way of turning a path from the terminal into a candidate file
+///
+/// Strategies are tried in the order configured in `Config::strategies`; the first
+/// candidate that exists wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Relative to the pane's cwd (home and absolute paths are taken as-is)
+    Cwd,
+    /// Relative to each directory above the cwd, up to the git root
+    Ancestors,
+    /// Relative to each configured search root
+    SearchRoots,
+    /// Any file in the cwd's git repo whose path ends with the (unprefixed) path
+    Suffix,
+}
+
+pub const DEFAULT_STRATEGIES: &[Strategy] = &[
+    Strategy::Cwd,
+    Strategy::Ancestors,
+    Strategy::SearchRoots,
+    Strategy::Suffix,
+];
+
+impl Strategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Cwd => "cwd",
+            Strategy::Ancestors => "ancestors",
+            Strategy::SearchRoots => "search_roots",
+            Strategy::Suffix => "suffix",
+        }
+    }
+
+    /// Paths this strategy would try for `path`, most likely first
+    ///
+    /// Anything worth knowing about how the candidates were found goes in `trace`.
+    pub fn candidates(
+        &self,
+        path: &str,
+        cwd: &Path,
+        config: &Config,
+        trace: &mut Vec<String>,
+    ) -> Vec<PathBuf> {
+        let relative = !path.starts_with('~') && Path::new(path).is_relative();
+        let mode = config.normalization;
+        let unprefixed = relative && !path.starts_with("./") && !path.starts_with("../");
+
+        match self {
+            Strategy::Cwd => vec![resolve_path_with(path, &cwd.to_string_lossy(), mode)],
+            Strategy::Ancestors if relative => {
+                let Some(root) = git::find_root(cwd) else {
+                    return Vec::new();
+                };
+                cwd.ancestors()
+                    .skip(1)
+                    .take_while(|dir| dir.starts_with(&root))
+                    .map(|dir| normalize_path_with(&dir.join(path), mode))
+                    .collect()
+            }
+            Strategy::SearchRoots if relative => config
+                .search_roots
+                .iter()
+                .map(|root| normalize_path_with(&root.join(path), mode))
+                .collect(),
+            Strategy::Suffix if unprefixed => {
+                let Some(root) = git::find_root(cwd) else {
+                    return Vec::new();
+                };
+                let mut index = match RepoIndex::load(&root, config.cache_dir.as_deref()) {
+                    Ok(index) => index,
+                    Err(e) => {
+                        trace.push(format!(
+                            "{}: cannot index {}: {}",
+                            self.name(),
+                            root.display(),
+                            e
+                        ));
+                        return Vec::new();
+                    }
+                };
+
+                let mut found = index.find_suffix(path, cwd);
+                // The cache predates files created since it was written; look again before giving up
+                if found.is_empty() && index.from_cache && index.refresh().is_ok() {
+                    trace.push(format!("{}: refreshed cached index", self.name()));
+                    found = index.find_suffix(path, cwd);
+                }
+                found
+            }
+            Strategy::Ancestors | Strategy::SearchRoots | Strategy::Suffix => Vec::new(),
+        }
+    }
+}
+
+/// Most alternatives reported alongside the best match, e.g. for `mod.rs` in a big repo
+pub const MAX_ALTERNATIVES: usize = 20;
+
+/// The outcome of running the strategy chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    pub path: PathBuf,
+    /// The strategy whose candidate exists, or None if nothing did
+    pub strategy: Option<Strategy>,
+    /// Other existing candidates from the same strategy, in its ranking order
+    pub alternatives: Vec<PathBuf>,
+    pub trace: Vec<String>,
+}
+
+/// Try each configured strategy in turn and return the first candidate that exists
+///
+/// When nothing exists, the cwd-relative path is returned so the caller can still
+/// open it (e.g. to create the file).
+pub fn resolve(path: &str, cwd: &Path, config: &Config) -> Found {
+    let mut trace = Vec::new();
+    // Candidates that didn't exist, for the case-insensitive pass
+    let mut missing = Vec::new();
+
+    for strategy in &config.strategies {
+        let mut candidates = strategy
+            .candidates(path, cwd, config, &mut trace)
+            .into_iter();
+
+        while let Some(candidate) = candidates.next() {
+            if candidate.exists() {
+                trace.push(format!(
+                    "{}: found {}",
+                    strategy.name(),
+                    candidate.display()
+                ));
+
+                let alternatives: Vec<PathBuf> = candidates
+                    .filter(|other| other.exists())
+                    .take(MAX_ALTERNATIVES)
+                    .collect();
+                if !alternatives.is_empty() {
+                    trace.push(format!(
+                        "{}: {} other match(es), first is {}",
+                        strategy.name(),
+                        alternatives.len(),
+                        alternatives[0].display()
+                    ));
+                }
+
+                return Found {
+                    path: candidate,
+                    strategy: Some(*strategy),
+                    alternatives,
+                    trace,
+                };
+            }
+            trace.push(format!("{}: no {}", strategy.name(), candidate.display()));
+            missing.push((*strategy, candidate));
+        }
+    }
+
+    // Only once no candidate exists with the exact case, so an exact match always wins
+    if config.case_insensitive {
+        for (strategy, candidate) in missing {
+            if let Some(found) = find_case_insensitive(&candidate) {
+                trace.push(format!(
+                    "{}: found {} ignoring case",
+                    strategy.name(),
+                    found.display()
+                ));
+                return Found {
+                    path: found,
+                    strategy: Some(strategy),
+                    alternatives: Vec::new(),
+                    trace,
+                };
+            }
+        }
+    }
+
+    Found {
+        path: resolve_path_with(path, &cwd.to_string_lossy(), config.normalization),
+        strategy: None,
+        alternatives: Vec::new(),
+        trace,
+    }
+}
+
+/// Map a missing absolute path from another machine onto the cwd's repo
+///
+/// Drops leading components until the rest exists below the git root, keeping the
+/// longest tail, so `/home/runner/work/repo/repo/src/x.rs` becomes `<root>/src/x.rs`.
+/// A file name alone is too common to go on: a one-component tail only counts when the
+/// dropped prefix ends in a directory named like the repo. Returns the dropped prefix
+/// and the local path.
+pub fn infer_rewrite(path: &Path, cwd: &Path) -> Option<(PathBuf, PathBuf)> {
+    let root = git::find_root(cwd)?;
+    if !path.is_absolute() || path.starts_with(&root) || path.exists() {
+        return None;
+    }
+
+    let components: Vec<_> = path.components().skip(1).collect();
+    (1..components.len()).find_map(|i| {
+        let tail: PathBuf = components[i..].iter().collect();
+        if components.len() - i < 2 && components[i - 1].as_os_str() != root.file_name()? {
+            return None;
+        }
+        let local = root.join(&tail);
+        local.exists().then(|| {
+            let prefix = Path::new("/").join(components[..i].iter().collect::<PathBuf>());
+            (prefix, local)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A repo with `src/lib.rs` at its root and an empty `crates/app/tests` below it
+    fn repo() -> (tempfile::TempDir, PathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("crates/app/tests")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        (tmp, root)
+    }
+
+    #[test]
+    fn test_infer_rewrite_keeps_longest_tail() {
+        let (_tmp, root) = repo();
+        fs::write(root.join("lib.rs"), "").unwrap();
+
+        assert_eq!(
+            infer_rewrite(Path::new("/home/runner/work/repo/repo/src/lib.rs"), &root),
+            Some((
+                PathBuf::from("/home/runner/work/repo/repo"),
+                root.join("src/lib.rs")
+            ))
+        );
+        assert_eq!(
+            infer_rewrite(Path::new("/app/src/lib.rs"), &root),
+            Some((PathBuf::from("/app"), root.join("src/lib.rs")))
+        );
+        // A file name alone only counts below a directory named like the repo
+        assert_eq!(
+            infer_rewrite(Path::new("/ci/repo/lib.rs"), &root),
+            Some((PathBuf::from("/ci/repo"), root.join("lib.rs")))
+        );
+        assert_eq!(
+            infer_rewrite(Path::new("/other/project/lib.rs"), &root),
+            None
+        );
+
+        assert_eq!(infer_rewrite(Path::new("/app/src/main.rs"), &root), None);
+        // A new file inside the repo isn't someone else's checkout
+        assert_eq!(infer_rewrite(&root.join("src/new/lib.rs"), &root), None);
+        assert_eq!(infer_rewrite(Path::new("src/lib.rs"), &root), None);
+    }
+
+    #[test]
+    fn test_case_insensitive_fallback_after_exact_matches() {
+        let (_tmp, root) = repo();
+        let cwd = root.join("crates/app");
+        fs::write(cwd.join("Lib.rs"), "").unwrap();
+        fs::write(root.join("lib.rs"), "").unwrap();
+
+        // Exact-case lib.rs via ancestors beats the cwd's case-insensitive match
+        let found = resolve("lib.rs", &cwd, &Config::default());
+        assert_eq!(found.path, root.join("lib.rs"));
+        assert_eq!(found.strategy, Some(Strategy::Ancestors));
+
+        let found = resolve("SRC/LIB.rs", &root, &Config::default());
+        assert_eq!(found.path, root.join("src/lib.rs"));
+        assert_eq!(found.strategy, Some(Strategy::Cwd));
+
+        let config = Config {
+            case_insensitive: false,
+            ..Default::default()
+        };
+        assert_eq!(resolve("SRC/LIB.rs", &root, &config).strategy, None);
+    }
+
+    #[test]
+    fn test_cwd_wins_when_it_exists() {
+        let (_tmp, root) = repo();
+
+        let found = resolve("src/lib.rs", &root, &Config::default());
+        assert_eq!(found.path, root.join("src/lib.rs"));
+        assert_eq!(found.strategy, Some(Strategy::Cwd));
+    }
+
+    #[test]
+    fn test_ancestors_up_to_git_root() {
+        let (_tmp, root) = repo();
+        let cwd = root.join("crates/app/tests");
+
+        let found = resolve("src/lib.rs", &cwd, &Config::default());
+        assert_eq!(found.path, root.join("src/lib.rs"));
+        assert_eq!(found.strategy, Some(Strategy::Ancestors));
+        assert_eq!(
+            found.trace,
+            [
+                format!("cwd: no {}", cwd.join("src/lib.rs").display()),
+                format!(
+                    "ancestors: no {}",
+                    root.join("crates/app/src/lib.rs").display()
+                ),
+                format!("ancestors: no {}", root.join("crates/src/lib.rs").display()),
+                format!("ancestors: found {}", root.join("src/lib.rs").display()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ancestors_stop_at_git_root() {
+        let (tmp, root) = repo();
+        fs::write(tmp.path().join("outside.rs"), "").unwrap();
+
+        let found = resolve("outside.rs", &root.join("src"), &Config::default());
+        assert_eq!(found.strategy, None);
+        assert_eq!(found.path, root.join("src/outside.rs"));
+    }
+
+    #[test]
+    fn test_search_roots_after_ancestors() {
+        let (tmp, root) = repo();
+        let other = tmp.path().join("other");
+        fs::create_dir_all(other.join("lib")).unwrap();
+        fs::write(other.join("lib/util.rb"), "").unwrap();
+
+        let config = Config {
+            search_roots: vec![other.clone()],
+            ..Default::default()
+        };
+        let found = resolve("lib/util.rb", &root.join("src"), &config);
+        assert_eq!(found.path, other.join("lib/util.rb"));
+        assert_eq!(found.strategy, Some(Strategy::SearchRoots));
+    }
+
+    #[test]
+    fn test_configured_order_is_respected() {
+        let (_tmp, root) = repo();
+        let config = Config {
+            strategies: vec![Strategy::SearchRoots],
+            ..Default::default()
+        };
+
+        let found = resolve("src/lib.rs", &root, &config);
+        assert_eq!(found.strategy, None);
+        assert!(found.trace.is_empty());
+    }
+
+    #[test]
+    fn test_suffix_search_in_git_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        crate::git::tests::init_repo(&root, &["app/models/user.rb", "spec/user_spec.rb"]);
+        let config = Config {
+            cache_dir: None,
+            ..Default::default()
+        };
+
+        let found = resolve("models/user.rb", &root.join("spec"), &config);
+        assert_eq!(found.path, root.join("app/models/user.rb"));
+        assert_eq!(found.strategy, Some(Strategy::Suffix));
+        assert!(found.alternatives.is_empty());
+
+        // Explicitly relative paths mean what they say
+        let found = resolve("./models/user.rb", &root.join("spec"), &config);
+        assert_eq!(found.strategy, None);
+    }
+
+    #[test]
+    fn test_alternatives_from_the_winning_strategy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("repo");
+        crate::git::tests::init_repo(
+            &root,
+            &["app/models/user.rb", "spec/models/user.rb", "lib/user.rb"],
+        );
+        let config = Config {
+            cache_dir: None,
+            ..Default::default()
+        };
+
+        let found = resolve("models/user.rb", &root, &config);
+        assert_eq!(found.path, root.join("app/models/user.rb"));
+        assert_eq!(found.alternatives, [root.join("spec/models/user.rb")]);
+    }
+
+    #[test]
+    fn test_absolute_paths_only_use_cwd_strategy() {
+        let (_tmp, root) = repo();
+        let config = Config {
+            search_roots: vec![root.clone()],
+            ..Default::default()
+        };
+
+        let found = resolve("/nonexistent/src/lib.rs", &root.join("src"), &config);
+        assert_eq!(found.path, PathBuf::from("/nonexistent/src/lib.rs"));
+        assert_eq!(found.trace, ["cwd: no /nonexistent/src/lib.rs"]);
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::expand::env_dir;
+
+/// Prefix rustc bakes into standard library paths in panics and backtraces
+pub const RUSTC_PREFIX: &str = "/rustc/";
+
+/// Marker for crate sources unpacked by cargo, e.g. in `~/.cargo/registry/src/`
+const REGISTRY_SRC: &str = "/.cargo/registry/src/";
+
+/// Split `/rustc/<commit-hash>/library/core/src/option.rs` into the hash and the path
+/// below the rust source root
+pub fn std_relative(path: &str) -> Option<(&str, &str)> {
+    let (hash, relative) = path.strip_prefix(RUSTC_PREFIX)?.split_once('/')?;
+    let is_hash = hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit());
+    (is_hash && !relative.is_empty()).then_some((hash, relative))
+}
+
+/// Where the `rust-src` component puts the standard library sources
+pub fn rust_src(sysroot: &Path) -> PathBuf {
+    sysroot.join("lib/rustlib/src/rust")
+}
+
+/// The active toolchain's sysroot, unless one is configured
+pub fn sysroot(configured: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(sysroot) = configured {
+        return Ok(sysroot.to_path_buf());
+    }
+    let out = rustc(None, "--print")
+        .arg("sysroot")
+        .output()
+        .map_err(|e| format!("cannot run rustc: {}", e))?;
+    if !out.status.success() {
+        return Err(format!(
+            "rustc --print sysroot failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(PathBuf::from(String::from_utf8_lossy(&out.stdout).trim()))
+}
+
+/// The commit the toolchain in `sysroot` was built from, per `rustc -vV`
+pub fn commit_hash(sysroot: &Path) -> Option<String> {
+    let out = rustc(Some(sysroot), "-vV").output().ok()?;
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("commit-hash: "))
+        .map(|hash| hash.trim().to_string())
+}
+
+/// rustc from `sysroot` when it has one, else whichever is on PATH
+fn rustc(sysroot: Option<&Path>, arg: &str) -> Command {
+    let program = sysroot
+        .map(|sysroot| sysroot.join("bin/rustc"))
+        .filter(|rustc| rustc.is_file())
+        .unwrap_or_else(|| PathBuf::from("rustc"));
+    let mut command = Command::new(program);
+    command.arg(arg);
+    command
+}
+
+/// The path below cargo's registry source dir, e.g.
+/// `index.crates.io-6f17d22bba15001f/serde-1.0.210/src/de/mod.rs`
+pub fn registry_relative(path: &str) -> Option<&str> {
+    let pos = path.find(REGISTRY_SRC)?;
+    Some(&path[pos + REGISTRY_SRC.len()..]).filter(|relative| !relative.is_empty())
+}
+
+/// `$CARGO_HOME/registry/src`, defaulting to `~/.cargo`
+pub fn local_registry_src() -> Option<PathBuf> {
+    let cargo_home = env_dir("CARGO_HOME", ".cargo")?;
+    Some(cargo_home.join("registry/src"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf";
+
+    #[test]
+    fn test_std_relative() {
+        let path = format!("/rustc/{}/library/core/src/option.rs", HASH);
+        assert_eq!(
+            std_relative(&path),
+            Some((HASH, "library/core/src/option.rs"))
+        );
+
+        assert_eq!(
+            std_relative("/rustc/abc123/library/core/src/option.rs"),
+            None
+        );
+        assert_eq!(std_relative(&format!("/rustc/{}/", HASH)), None);
+        assert_eq!(std_relative("/home/user/rustc/x.rs"), None);
+    }
+
+    #[test]
+    fn test_rust_src() {
+        assert_eq!(
+            rust_src(Path::new("/toolchains/stable")),
+            PathBuf::from("/toolchains/stable/lib/rustlib/src/rust")
+        );
+        assert_eq!(
+            sysroot(Some(Path::new("/toolchains/stable"))),
+            Ok(PathBuf::from("/toolchains/stable"))
+        );
+    }
+
+    #[test]
+    fn test_registry_relative() {
+        assert_eq!(
+            registry_relative(
+                "/home/runner/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.210/src/de/mod.rs"
+            ),
+            Some("index.crates.io-6f17d22bba15001f/serde-1.0.210/src/de/mod.rs")
+        );
+        assert_eq!(registry_relative("/home/runner/.cargo/registry/src/"), None);
+        assert_eq!(registry_relative("/home/runner/src/main.rs"), None);
+    }
+}
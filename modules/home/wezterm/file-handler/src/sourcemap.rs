@@ -0,0 +1,363 @@
+//! Source map (v3) lookups, so locations in bundled JavaScript open the original source
+//!
+//! The map is found through the generated file's `//# sourceMappingURL=` comment, which
+//! may be a relative path or an inline base64 `data:` URL, or else a sibling `.map` file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::normalize_path;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Extensions of generated files worth looking for a source map for
+pub const GENERATED_EXTENSIONS: &[&str] = &["js", "mjs", "cjs"];
+
+/// A position in an original source, 1-based like the rest of the handler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Original {
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMap {
+    version: u32,
+    #[serde(default)]
+    source_root: Option<String>,
+    #[serde(default)]
+    sources: Vec<Option<String>>,
+    #[serde(default)]
+    mappings: Option<String>,
+}
+
+/// (generated column, source index, original line, original column), all 0-based
+type Segment = (u32, u32, u32, u32);
+
+/// A parsed source map whose relative sources are taken against `base_dir`
+#[derive(Debug)]
+pub struct SourceMap {
+    base_dir: PathBuf,
+    source_root: Option<String>,
+    sources: Vec<Option<String>>,
+    /// Segments for each generated line, sorted by generated column
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    /// Parse a map; sources are relative to `base_dir`, the directory the map lives in
+    pub fn parse(json: &str, base_dir: &Path) -> Result<Self, String> {
+        let raw: RawMap =
+            serde_json::from_str(json).map_err(|e| format!("invalid source map: {}", e))?;
+        if raw.version != 3 {
+            return Err(format!("unsupported source map version {}", raw.version));
+        }
+        let mappings = raw
+            .mappings
+            .ok_or("index source maps (with `sections`) are not supported")?;
+
+        Ok(SourceMap {
+            base_dir: base_dir.to_path_buf(),
+            source_root: raw.source_root.filter(|root| !root.is_empty()),
+            sources: raw.sources,
+            lines: decode_mappings(&mappings)?,
+        })
+    }
+
+    /// Find and parse the map for `generated`, or None when it doesn't have one
+    pub fn for_generated(generated: &Path) -> Result<Option<Self>, String> {
+        let dir = generated.parent().unwrap_or(Path::new("/"));
+        let contents = fs::read_to_string(generated)
+            .map_err(|e| format!("cannot read {}: {}", generated.display(), e))?;
+
+        match mapping_url(&contents) {
+            Some(url) if url.starts_with("data:") => {
+                let json = decode_data_url(url)?;
+                SourceMap::parse(&json, dir).map(Some)
+            }
+            Some(url) => {
+                let path = normalize_path(&dir.join(url.split(['?', '#']).next().unwrap_or(url)));
+                SourceMap::read(&path).map(Some)
+            }
+            None => {
+                let mut sibling = generated.as_os_str().to_owned();
+                sibling.push(".map");
+                let sibling = PathBuf::from(sibling);
+                if sibling.is_file() {
+                    SourceMap::read(&sibling).map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        SourceMap::parse(&json, path.parent().unwrap_or(Path::new("/")))
+    }
+
+    /// The original position of 1-based `line` and `column` in the generated file
+    ///
+    /// Without a column the first mapping on the line is used.
+    pub fn lookup(&self, line: u32, column: Option<u32>) -> Option<Original> {
+        let segments = self.lines.get(line.checked_sub(1)? as usize)?;
+        let segment = match column {
+            Some(column) => segments
+                .iter()
+                .take_while(|(generated, ..)| *generated < column.max(1))
+                .last(),
+            None => segments.first(),
+        }?;
+
+        let (_, source, original_line, original_column) = *segment;
+        let source = self.sources.get(source as usize)?.as_deref()?;
+        Some(Original {
+            path: self.source_path(source),
+            line: original_line + 1,
+            column: original_column + 1,
+        })
+    }
+
+    fn source_path(&self, source: &str) -> PathBuf {
+        // Bundlers use URLs like "webpack://app/./src/x.ts"; the part after the
+        // namespace is relative to the project
+        let source = match source.strip_prefix("webpack://") {
+            Some(rest) => rest.split_once('/').map_or(rest, |(_, path)| path),
+            None => source.strip_prefix("file://").unwrap_or(source),
+        };
+        let full = match &self.source_root {
+            Some(root) => format!("{}/{}", root.trim_end_matches('/'), source),
+            None => source.to_string(),
+        };
+        normalize_path(&self.base_dir.join(full))
+    }
+}
+
+/// The URL from the last `//# sourceMappingURL=` (or legacy `//@`) comment
+fn mapping_url(contents: &str) -> Option<&str> {
+    contents.lines().rev().find_map(|line| {
+        let line = line.trim();
+        let comment = line
+            .strip_prefix("//# sourceMappingURL=")
+            .or_else(|| line.strip_prefix("//@ sourceMappingURL="))?;
+        Some(comment.trim()).filter(|url| !url.is_empty())
+    })
+}
+
+/// Decode `data:application/json;base64,...` (or an unencoded data URL)
+fn decode_data_url(url: &str) -> Result<String, String> {
+    let (header, data) = url
+        .split_once(',')
+        .ok_or("malformed data URL in sourceMappingURL")?;
+    if !header.ends_with(";base64") {
+        return Ok(data.to_string());
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in data.bytes().filter(|&b| b != b'=') {
+        let value = base64_value(byte).ok_or("invalid base64 in sourceMappingURL")?;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| "inline source map is not UTF-8".to_string())
+}
+
+fn base64_value(byte: u8) -> Option<u32> {
+    BASE64.iter().position(|&b| b == byte).map(|i| i as u32)
+}
+
+/// Decode the `mappings` string into per-line segments with absolute values
+fn decode_mappings(mappings: &str) -> Result<Vec<Vec<Segment>>, String> {
+    // Every field except the generated column is relative to the previous segment,
+    // across lines
+    let (mut source, mut line, mut column) = (0i64, 0i64, 0i64);
+    let mut lines = Vec::new();
+
+    for generated_line in mappings.split(';') {
+        let mut generated_column = 0i64;
+        let mut segments = Vec::new();
+
+        for segment in generated_line.split(',').filter(|s| !s.is_empty()) {
+            let fields = decode_vlq(segment)?;
+            generated_column += fields[0];
+            match fields.len() {
+                1 => continue,
+                4 | 5 => {
+                    source += fields[1];
+                    line += fields[2];
+                    column += fields[3];
+                }
+                n => return Err(format!("source map segment with {} fields", n)),
+            }
+
+            let value = |v: i64| u32::try_from(v).map_err(|_| "negative source map position");
+            segments.push((
+                value(generated_column)?,
+                value(source)?,
+                value(line)?,
+                value(column)?,
+            ));
+        }
+
+        segments.sort_by_key(|segment| segment.0);
+        lines.push(segments);
+    }
+
+    Ok(lines)
+}
+
+/// Decode one segment of base64 VLQ numbers
+fn decode_vlq(segment: &str) -> Result<Vec<i64>, String> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+
+    for byte in segment.bytes() {
+        let digit = base64_value(byte)
+            .ok_or_else(|| format!("invalid character in source map segment '{}'", segment))?
+            as i64;
+        if shift > 60 {
+            return Err(format!("source map value too large in '{}'", segment));
+        }
+        value += (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            continue;
+        }
+
+        let magnitude = value >> 1;
+        values.push(if value & 1 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        });
+        (value, shift) = (0, 0);
+    }
+
+    if shift != 0 || values.is_empty() {
+        return Err(format!("truncated source map segment '{}'", segment));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maps "dist/out.js" back to "../src/a.ts" and "../src/b.ts"
+    const MAP: &str = r#"{
+        "version": 3,
+        "file": "out.js",
+        "sources": ["../src/a.ts", "../src/b.ts"],
+        "names": [],
+        "mappings": "AAAA,SAAS;ACCT,IAAI,KAAK;KACL"
+    }"#;
+
+    #[test]
+    fn test_decode_vlq() {
+        assert_eq!(decode_vlq("A").unwrap(), [0]);
+        assert_eq!(decode_vlq("C").unwrap(), [1]);
+        assert_eq!(decode_vlq("D").unwrap(), [-1]);
+        assert_eq!(decode_vlq("gB").unwrap(), [16]);
+        assert_eq!(decode_vlq("AAgBC").unwrap(), [0, 0, 16, 1]);
+        assert!(decode_vlq("g").is_err());
+        assert!(decode_vlq("A!").is_err());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let map = SourceMap::parse(MAP, Path::new("/p/dist")).unwrap();
+
+        let at = |path: &str, line, column| Original {
+            path: PathBuf::from(path),
+            line,
+            column,
+        };
+        assert_eq!(map.lookup(1, Some(1)), Some(at("/p/src/a.ts", 1, 1)));
+        // Column 10 is inside the segment that starts at generated column 9 (0-based)
+        assert_eq!(map.lookup(1, Some(12)), Some(at("/p/src/a.ts", 1, 10)));
+        assert_eq!(map.lookup(2, Some(6)), Some(at("/p/src/b.ts", 2, 5)));
+        assert_eq!(map.lookup(2, Some(11)), Some(at("/p/src/b.ts", 2, 10)));
+        assert_eq!(map.lookup(2, None), Some(at("/p/src/b.ts", 2, 1)));
+
+        assert_eq!(map.lookup(3, Some(6)), Some(at("/p/src/b.ts", 3, 5)));
+
+        // Before the first segment on the line, and past the last line
+        assert_eq!(map.lookup(3, Some(5)), None);
+        assert_eq!(map.lookup(9, Some(1)), None);
+    }
+
+    #[test]
+    fn test_source_root_and_bundler_urls() {
+        let map = SourceMap::parse(
+            r#"{"version":3,"sourceRoot":"/app","sources":["webpack://app/./src/x.ts"],"mappings":"AAAA"}"#,
+            Path::new("/p/dist"),
+        )
+        .unwrap();
+        assert_eq!(
+            map.lookup(1, None).unwrap().path,
+            PathBuf::from("/app/src/x.ts")
+        );
+
+        let error = SourceMap::parse(r#"{"version":3,"sections":[]}"#, Path::new("/")).unwrap_err();
+        assert!(error.contains("not supported"), "{}", error);
+    }
+
+    #[test]
+    fn test_inline_and_sibling_maps() {
+        let dir = tempfile::tempdir().unwrap();
+        let dist = dir.path().join("dist");
+        fs::create_dir_all(&dist).unwrap();
+
+        // A sibling server.js.map without a comment
+        let sibling = dist.join("server.js");
+        fs::write(&sibling, "console.log(1)\n").unwrap();
+        fs::write(dist.join("server.js.map"), MAP).unwrap();
+        let map = SourceMap::for_generated(&sibling).unwrap().unwrap();
+        assert_eq!(
+            map.lookup(1, Some(1)).unwrap().path,
+            dir.path().join("src/a.ts")
+        );
+
+        // An inline map takes precedence over the sibling
+        let inline = dist.join("inline.js");
+        let json = r#"{"version":3,"sources":["../src/c.ts"],"mappings":"AAAA"}"#;
+        let url = format!(
+            "data:application/json;charset=utf-8;base64,{}",
+            encode(json)
+        );
+        fs::write(&inline, format!("x()\n//# sourceMappingURL={}\n", url)).unwrap();
+        let map = SourceMap::for_generated(&inline).unwrap().unwrap();
+        assert_eq!(
+            map.lookup(1, Some(1)).unwrap().path,
+            dir.path().join("src/c.ts")
+        );
+
+        let plain = dist.join("plain.js");
+        fs::write(&plain, "x()\n").unwrap();
+        assert!(SourceMap::for_generated(&plain).unwrap().is_none());
+    }
+
+    fn encode(text: &str) -> String {
+        let mut out = String::new();
+        for chunk in text.as_bytes().chunks(3) {
+            let n = chunk.iter().fold(0u32, |n, &b| (n << 8) | b as u32) << (8 * (3 - chunk.len()));
+            for i in 0..=chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+            out.push_str(&"=".repeat(3 - chunk.len()));
+        }
+        out
+    }
+}
@@ -1,107 +1,585 @@
+use wezterm_file_handler::config::Config;
+use wezterm_file_handler::plan::{Plan, Resolution};
 use wezterm_file_handler::{extract_path_and_location, resolve_path};
 use std::path::PathBuf;
 
+/// The default config with its file index cache in `cache`, so the suite never writes
+/// into the real ~/.cache
+fn test_config(cache: &tempfile::TempDir) -> Config {
+    Config {
+        cache_dir: Some(cache.path().to_path_buf()),
+        ..Default::default()
+    }
+}
+
 #[test]
 fn test_relative_path_with_dot_slash() {
-    let (file_path, line_col) = extract_path_and_location("./config.lua");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./config.lua");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/config.lua"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_path_with_line_number() {
-    let (file_path, line_col) = extract_path_and_location("./config.lua:10");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./config.lua:10");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/config.lua"));
-    assert_eq!(line_col, Some("10".to_string()));
+    assert_eq!(file_ref.line, Some(10));
+    assert_eq!(file_ref.column, None);
 }
 
 #[test]
 fn test_path_with_line_and_column() {
-    let (file_path, line_col) = extract_path_and_location("./src/main.rs:42:15");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./src/main.rs:42:15");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/src/main.rs"));
-    assert_eq!(line_col, Some("42:15".to_string()));
+    assert_eq!(file_ref.line, Some(42));
+    assert_eq!(file_ref.column, Some(15));
 }
 
 #[test]
 fn test_unprefixed_relative_path() {
-    let (file_path, line_col) = extract_path_and_location("spec/spec_helper.rb");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("spec/spec_helper.rb");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/spec/spec_helper.rb"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_parent_directory_path() {
-    let (file_path, line_col) = extract_path_and_location("../config.lua");
-    let resolved = resolve_path(&file_path, "/Users/test/project/subdir");
+    let file_ref = extract_path_and_location("../config.lua");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project/subdir");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/config.lua"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_absolute_path() {
-    let (file_path, line_col) = extract_path_and_location("/etc/hosts");
-    let resolved = resolve_path(&file_path, "/any/directory");
+    let file_ref = extract_path_and_location("/etc/hosts");
+    let resolved = resolve_path(&file_ref.path, "/any/directory");
 
     assert_eq!(resolved, PathBuf::from("/etc/hosts"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_home_directory_path() {
-    let (file_path, line_col) = extract_path_and_location("~/.zshrc");
-    let resolved = resolve_path(&file_path, "/tmp");
+    let file_ref = extract_path_and_location("~/.zshrc");
+    let resolved = resolve_path(&file_ref.path, "/tmp");
 
     // Should expand to actual home directory
     assert!(resolved.is_absolute());
     assert!(resolved.to_string_lossy().ends_with(".zshrc"));
     assert!(!resolved.to_string_lossy().contains("~"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_ruby_stack_trace_with_in() {
-    let (file_path, line_col) = extract_path_and_location("./lib/file.rb:40:in");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./lib/file.rb:40:in");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/lib/file.rb"));
-    assert_eq!(line_col, Some("40:in".to_string()));
+    assert_eq!(file_ref.line, Some(40));
+    assert_eq!(file_ref.column, None);
 }
 
 #[test]
 fn test_complex_nested_path() {
-    let (file_path, line_col) = extract_path_and_location("./lib/config_spec/runtime/suite_runner.rb:63");
-    let resolved = resolve_path(&file_path, "/Users/test/opencounter/opencounter");
+    let file_ref = extract_path_and_location("./lib/config_spec/runtime/suite_runner.rb:63");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/opencounter/opencounter");
 
     assert_eq!(
         resolved,
         PathBuf::from("/Users/test/opencounter/opencounter/lib/config_spec/runtime/suite_runner.rb")
     );
-    assert_eq!(line_col, Some("63".to_string()));
+    assert_eq!(file_ref.line, Some(63));
+    assert_eq!(file_ref.column, None);
 }
 
 #[test]
 fn test_path_with_no_extension() {
-    let (file_path, line_col) = extract_path_and_location("./Makefile");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./Makefile");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/Makefile"));
-    assert_eq!(line_col, None);
+    assert!(!file_ref.has_location());
 }
 
 #[test]
 fn test_dotfile_path() {
-    let (file_path, line_col) = extract_path_and_location("./.gitignore:5");
-    let resolved = resolve_path(&file_path, "/Users/test/project");
+    let file_ref = extract_path_and_location("./.gitignore:5");
+    let resolved = resolve_path(&file_ref.path, "/Users/test/project");
 
     assert_eq!(resolved, PathBuf::from("/Users/test/project/.gitignore"));
-    assert_eq!(line_col, Some("5".to_string()));
+    assert_eq!(file_ref.line, Some(5));
+    assert_eq!(file_ref.column, None);
+}
+
+#[test]
+fn test_unknown_user_home_is_an_error() {
+    let cache = tempfile::tempdir().unwrap();
+    let result = Resolution::new(
+        "~no-such-user-wezterm-fh/.bashrc",
+        "/tmp",
+        &test_config(&cache),
+    );
+
+    assert_eq!(
+        result,
+        Err("unknown user 'no-such-user-wezterm-fh'".to_string())
+    );
+}
+
+#[test]
+fn test_pane_vars_expand_before_resolving() {
+    let cache = tempfile::tempdir().unwrap();
+    let mut config = test_config(&cache);
+    config
+        .vars
+        .insert("PROJECT_ROOT".to_string(), "/srv/app".to_string());
+
+    let resolution = Resolution::new("${PROJECT_ROOT}/lib/x.rb:3", "/tmp", &config).unwrap();
+    assert_eq!(resolution.path, PathBuf::from("/srv/app/lib/x.rb"));
+    assert_eq!(resolution.file_ref.line, Some(3));
+
+    let result = Resolution::new("${NO_SUCH_VAR_WEZTERM_FH}/x.rb", "/tmp", &config);
+    assert_eq!(
+        result,
+        Err(
+            "undefined variable 'NO_SUCH_VAR_WEZTERM_FH' in '${NO_SUCH_VAR_WEZTERM_FH}/x.rb'"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_nix_store_source_maps_to_flake_checkout() {
+    let cache = tempfile::tempdir().unwrap();
+    let checkout = tempfile::tempdir().unwrap();
+    let file = checkout.path().join("modules/home/wezterm/default.nix");
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    std::fs::write(&file, "{ }").unwrap();
+
+    let input =
+        "/nix/store/0c2kzyhn4gwmmh1yx5q9bsd7ij8n8dff-source/modules/home/wezterm/default.nix:42:3";
+    let mut config = Config {
+        flake_checkouts: vec![checkout.path().to_path_buf()],
+        ..test_config(&cache)
+    };
+
+    let resolution = Resolution::new(input, "/tmp", &config).unwrap();
+    assert_eq!(resolution.path, file);
+    assert_eq!(resolution.file_ref.line, Some(42));
+    assert_eq!(resolution.file_ref.column, Some(3));
+
+    config.open_nix_store = true;
+    let resolution = Resolution::new(input, "/tmp", &config).unwrap();
+    assert!(resolution.path.starts_with("/nix/store"));
+}
+
+#[test]
+fn test_ci_path_is_inferred_onto_cwd_repo() {
+    let cache = tempfile::tempdir().unwrap();
+    let repo = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(repo.path().join(".git")).unwrap();
+    std::fs::create_dir_all(repo.path().join("src")).unwrap();
+    std::fs::write(repo.path().join("src/x.rs"), "").unwrap();
+    let cwd = repo.path().to_str().unwrap();
+
+    let input = "/home/runner/work/repo/repo/src/x.rs:7";
+    let resolution = Resolution::new(input, cwd, &test_config(&cache)).unwrap();
+    assert_eq!(resolution.path, repo.path().join("src/x.rs"));
+    assert!(resolution.exists);
+
+    let config = Config {
+        infer_rewrites: false,
+        ..test_config(&cache)
+    };
+    let resolution = Resolution::new(input, cwd, &config).unwrap();
+    assert!(!resolution.exists);
+
+    // Another project's file that only shares its name with one in the repo
+    std::fs::write(repo.path().join("README.md"), "").unwrap();
+    let resolution =
+        Resolution::new("/other/project/README.md:3", cwd, &test_config(&cache)).unwrap();
+    assert_eq!(resolution.path, PathBuf::from("/other/project/README.md"));
+    assert!(!resolution.exists);
+}
+
+#[test]
+fn test_rustc_backtrace_path_maps_to_rust_src() {
+    let cache = tempfile::tempdir().unwrap();
+    use std::os::unix::fs::PermissionsExt;
+
+    let sysroot = tempfile::tempdir().unwrap();
+    let option = sysroot
+        .path()
+        .join("lib/rustlib/src/rust/library/core/src/option.rs");
+    std::fs::create_dir_all(option.parent().unwrap()).unwrap();
+    std::fs::write(&option, "").unwrap();
+    let rustc = sysroot.path().join("bin/rustc");
+    std::fs::create_dir_all(rustc.parent().unwrap()).unwrap();
+    std::fs::write(
+        &rustc,
+        "#!/bin/sh\necho 'commit-hash: 90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&rustc, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = Config {
+        rust_sysroot: Some(sysroot.path().to_path_buf()),
+        ..test_config(&cache)
+    };
+    let matching =
+        "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/core/src/option.rs:935:5";
+    let resolution = Resolution::new(matching, "/tmp", &config).unwrap();
+    assert_eq!(resolution.path, option);
+    assert_eq!(resolution.file_ref.line, Some(935));
+    assert!(resolution.warnings.is_empty(), "{:?}", resolution.warnings);
+
+    let other = "/rustc/0000000000000000000000000000000000000000/library/core/src/option.rs:935:5";
+    let resolution = Resolution::new(other, "/tmp", &config).unwrap();
+    assert_eq!(resolution.path, option);
+    assert_eq!(resolution.warnings.len(), 1);
+    assert!(resolution.warnings[0].contains("lines may not match"));
+}
+
+#[test]
+fn test_generated_js_location_maps_to_original_source() {
+    let cache = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(project.path().join("dist")).unwrap();
+    std::fs::create_dir_all(project.path().join("src")).unwrap();
+    std::fs::write(project.path().join("src/server.ts"), "").unwrap();
+    std::fs::write(
+        project.path().join("dist/server.js"),
+        "x()\n//# sourceMappingURL=server.js.map\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.path().join("dist/server.js.map"),
+        r#"{"version":3,"sources":["../src/server.ts"],"mappings":"AAAA,gBAEgB"}"#,
+    )
+    .unwrap();
+    let cwd = project.path().to_str().unwrap();
+
+    // Column 20 falls in the segment for generated column 16, which maps to server.ts 3:17
+    let resolution = Resolution::new("dist/server.js:1:20", cwd, &test_config(&cache)).unwrap();
+    assert_eq!(resolution.path, project.path().join("src/server.ts"));
+    assert_eq!(resolution.file_ref.line, Some(3));
+    assert_eq!(resolution.file_ref.column, Some(17));
+
+    // No mapping on line 2: stay in the generated file
+    let resolution = Resolution::new("dist/server.js:2:1", cwd, &test_config(&cache)).unwrap();
+    assert_eq!(resolution.path, project.path().join("dist/server.js"));
+    assert_eq!(resolution.file_ref.line, Some(2));
+}
+
+#[test]
+fn test_plan_spawns_tab_for_directory() {
+    let cache = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(project.path().join("spec/models")).unwrap();
+    let config = Config {
+        directories: wezterm_file_handler::opener::DirectoryAction::Tab,
+        ..test_config(&cache)
+    };
+
+    let resolution = Resolution::new(
+        "./spec/models/:12",
+        project.path().to_str().unwrap(),
+        &config,
+    )
+    .unwrap();
+    let plan = Plan::new(resolution, &config).unwrap();
+
+    let dir = project.path().join("spec/models");
+    assert_eq!(plan.opener, "wezterm tab");
+    assert_eq!(
+        plan.argv,
+        ["wezterm", "cli", "spawn", "--cwd", dir.to_str().unwrap()]
+    );
+}
+
+#[test]
+fn test_missing_file_falls_back_to_closest_match() {
+    let cache = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(project.path().join("lib")).unwrap();
+    std::fs::write(project.path().join("lib/resolver.rb"), "").unwrap();
+    let cwd = project.path().to_str().unwrap();
+
+    let resolution = Resolution::new("./lib/resolvr.rb:4", cwd, &test_config(&cache)).unwrap();
+    assert!(!resolution.exists);
+    assert_eq!(
+        resolution.suggestion,
+        Some(project.path().join("lib/resolver.rb"))
+    );
+
+    let config = Config {
+        did_you_mean: wezterm_file_handler::fuzzy::DidYouMean::Open,
+        ..test_config(&cache)
+    };
+    let resolution = Resolution::new("./lib/resolvr.rb:4", cwd, &config).unwrap();
+    assert_eq!(resolution.path, project.path().join("lib/resolver.rb"));
+    assert!(resolution.exists);
+    assert_eq!(resolution.file_ref.line, Some(4));
+    assert!(
+        resolution.warnings[0].contains("opening"),
+        "{:?}",
+        resolution.warnings
+    );
+}
+
+#[test]
+fn test_plan_opens_location_in_configured_editor() {
+    let cache = tempfile::tempdir().unwrap();
+    let config = Config {
+        editor: Some("code".to_string()),
+        ..test_config(&cache)
+    };
+    let resolution =
+        Resolution::new("./lib/file.rb:40:in", "/Users/test/project", &config).unwrap();
+
+    assert_eq!(
+        resolution.path,
+        PathBuf::from("/Users/test/project/lib/file.rb")
+    );
+    assert!(!resolution.exists);
+
+    let plan = Plan::new(resolution, &config).unwrap();
+    assert_eq!(plan.opener, "vscode");
+    assert_eq!(
+        plan.argv,
+        ["code", "--goto", "/Users/test/project/lib/file.rb:40"]
+    );
+    assert_eq!(
+        plan.resolution.trace.first().map(String::as_str),
+        Some("parsed './lib/file.rb:40:in' as path './lib/file.rb' line 40")
+    );
+    assert!(plan
+        .resolution
+        .trace
+        .iter()
+        .any(|step| step == "chose opener 'vscode' (input has a location)"));
+}
+
+#[test]
+fn test_plan_selects_range_in_editor() {
+    let cache = tempfile::tempdir().unwrap();
+    let config = Config {
+        editor: Some("nvim".to_string()),
+        ..test_config(&cache)
+    };
+    let resolution =
+        Resolution::new("lib/file.rb#L10-L20", "/Users/test/project", &config).unwrap();
+    assert_eq!(resolution.file_ref.end, Some((20, None)));
+
+    let plan = Plan::new(resolution.clone(), &config).unwrap();
+    assert_eq!(
+        plan.argv,
+        [
+            "wezterm",
+            "cli",
+            "spawn",
+            "--",
+            "nvim",
+            "+call cursor(10, 1) | execute \"normal! V\" | call cursor(20, 1)",
+            "/Users/test/project/lib/file.rb"
+        ]
+    );
+
+    // VS Code can't select from the command line and opens at the start line
+    let config = Config {
+        editor: Some("code".to_string()),
+        ..test_config(&cache)
+    };
+    let plan = Plan::new(resolution, &config).unwrap();
+    assert_eq!(
+        plan.argv,
+        ["code", "--goto", "/Users/test/project/lib/file.rb:10"]
+    );
+    assert_eq!(
+        plan.resolution.trace.first().map(String::as_str),
+        Some("parsed 'lib/file.rb#L10-L20' as path 'lib/file.rb' line 10 to line 20")
+    );
+}
+
+#[test]
+fn test_plan_serializes_to_json() {
+    let cache = tempfile::tempdir().unwrap();
+    let config = Config {
+        editor: Some("zed".to_string()),
+        ..test_config(&cache)
+    };
+    let resolution = Resolution::new("src/main.rs:42:15", "/Users/test/project", &config).unwrap();
+    let plan = Plan::new(resolution, &config).unwrap();
+
+    let json = serde_json::to_value(&plan).unwrap();
+    assert_eq!(json["input"], "src/main.rs:42:15");
+    assert_eq!(json["file_ref"]["line"], 42);
+    assert_eq!(json["file_ref"]["column"], 15);
+    assert_eq!(json["path"], "/Users/test/project/src/main.rs");
+    assert_eq!(json["exists"], false);
+    assert_eq!(json["opener"], "zed");
+    assert_eq!(
+        json["argv"],
+        serde_json::json!(["zed", "/Users/test/project/src/main.rs:42:15"])
+    );
+}
+
+#[test]
+fn test_candidates_for_ambiguous_input() {
+    let cache = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    for file in ["app/models/user.rb", "spec/models/user.rb"] {
+        std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+        std::fs::write(root.join(file), "").unwrap();
+    }
+    let config = Config {
+        search_roots: vec![root.join("app"), root.join("spec")],
+        ..test_config(&cache)
+    };
+
+    let resolution = Resolution::new("models/user.rb:12:3", "/nonexistent", &config).unwrap();
+    let candidates = resolution.candidates(std::path::Path::new("/nonexistent"));
+
+    let inputs: Vec<&str> = candidates.iter().map(|c| c.input.as_str()).collect();
+    let expected = [
+        format!("{}:12:3", root.join("app/models/user.rb").display()),
+        format!("{}:12:3", root.join("spec/models/user.rb").display()),
+    ];
+    assert_eq!(inputs, expected);
+}
+
+#[test]
+fn test_resolution_reads_line_from_context() {
+    let cache = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::write(tmp.path().join("manage.py"), "").unwrap();
+    let cwd = tmp.path().to_str().unwrap();
+    let context = r#"  File "manage.py", line 22, in <module>"#;
+
+    let resolution =
+        Resolution::with_context("manage.py", Some(context), cwd, &test_config(&cache)).unwrap();
+    assert!(resolution.exists);
+    assert_eq!(resolution.file_ref.line, Some(22));
+    assert_eq!(
+        resolution.candidates(tmp.path())[0].input,
+        format!("{}:22", tmp.path().join("manage.py").display())
+    );
+}
+
+#[test]
+fn test_nix_context_is_read_as_prose_not_a_frame() {
+    let mut trace = Vec::new();
+    let file_ref = wezterm_file_handler::plan::parse_input(
+        "/cfg/x.nix",
+        Some("       at /cfg/x.nix:12:3:"),
+        &mut trace,
+    );
+    assert_eq!((file_ref.line, file_ref.column), (Some(12), Some(3)));
+    assert_eq!(
+        trace.last().map(String::as_str),
+        Some(
+            "read the location from the surrounding text (nix): path '/cfg/x.nix' line 12 column 3"
+        )
+    );
+}
+
+#[test]
+fn test_jvm_frame_resolves_through_package_directories() {
+    let cache = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    let sources = tmp.path().join("src/main/java");
+    std::fs::create_dir_all(sources.join("com/foo")).unwrap();
+    std::fs::write(sources.join("com/foo/Bar.java"), "").unwrap();
+    let config = Config {
+        search_roots: vec![sources.clone()],
+        ..test_config(&cache)
+    };
+    let context = "\tat com.foo.Bar.baz(Bar.java:42)";
+
+    let resolution =
+        Resolution::with_context("Bar.java:42", Some(context), "/nonexistent", &config).unwrap();
+    assert_eq!(resolution.path, sources.join("com/foo/Bar.java"));
+    assert_eq!(resolution.file_ref.line, Some(42));
+
+    // The whole frame works as input too
+    let resolution = Resolution::new(context, "/nonexistent", &config).unwrap();
+    assert_eq!(resolution.path, sources.join("com/foo/Bar.java"));
+}
+
+#[test]
+fn test_forge_url_opens_local_clone() {
+    let cache = tempfile::tempdir().unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    let clone = tmp.path().join("github.com/org/repo");
+    std::fs::create_dir_all(clone.join("app/models")).unwrap();
+    std::fs::write(clone.join("app/models/user.rb"), "").unwrap();
+    let git = |args: &[&str]| wezterm_file_handler::git::run(&clone, args).unwrap();
+    let commit = |message: &str| {
+        git(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            message,
+        ]);
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    commit("init");
+    git(&["branch", "old"]);
+    commit("next");
+    git(&["branch", "-M", "main"]);
+    let head = wezterm_file_handler::git::head(&clone).unwrap();
+
+    let config = Config {
+        clone_roots: vec![tmp.path().to_path_buf()],
+        system_opener: Some("xdg-open".to_string()),
+        ..test_config(&cache)
+    };
+    let url = "https://github.com/org/repo/blob/main/app/models/user.rb#L10-L20";
+
+    let resolution = Resolution::new(url, "/nonexistent", &config).unwrap();
+    assert_eq!(resolution.path, clone.join("app/models/user.rb"));
+    assert_eq!(resolution.file_ref.line, Some(10));
+    assert_eq!(resolution.file_ref.end, Some((20, None)));
+    assert!(resolution.warnings.is_empty(), "{:?}", resolution.warnings);
+
+    // A ref behind HEAD still opens the clone, with a warning
+    let old = url.replace("/main/", "/old/");
+    let resolution = Resolution::new(&old, "/nonexistent", &config).unwrap();
+    assert_eq!(resolution.path, clone.join("app/models/user.rb"));
+    assert!(
+        resolution.warnings[0].contains(&head[..9]),
+        "{:?}",
+        resolution.warnings
+    );
+
+    // Without a clone the URL goes to the browser
+    let other = url.replace("/org/", "/someone/");
+    let resolution = Resolution::new(&other, "/nonexistent", &config).unwrap();
+    assert_eq!(resolution.url.as_deref(), Some(other.as_str()));
+    let plan = Plan::new(resolution, &config).unwrap();
+    assert_eq!(plan.argv, ["xdg-open", other.as_str()]);
+
+    // So do web URLs that aren't forge files, rather than being resolved as paths
+    let pull = "https://github.com/org/repo/pull/12";
+    let resolution = Resolution::new(pull, clone.to_str().unwrap(), &config).unwrap();
+    assert_eq!(resolution.url.as_deref(), Some(pull));
+    assert!(!resolution.exists);
 }
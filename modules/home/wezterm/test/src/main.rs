@@ -198,6 +198,64 @@ fn main() {
         TestCase { input: "Files: src/main.rs, lib/utils.rs",
                    expected_match: Some("src/main.rs"),
                    description: "Unprefixed path followed by comma" },
+
+        // Paths starting with a shell variable
+        TestCase { input: "$HOME/.config/app.toml", expected_match: Some("$HOME/.config/app.toml"), description: "Path starting with $VAR" },
+        TestCase { input: "wrote ${XDG_CONFIG_HOME}/app/config.toml", expected_match: Some("${XDG_CONFIG_HOME}/app/config.toml"),
+                   description: "Path starting with ${VAR}" },
+        TestCase { input: "${PROJECT_ROOT:-/srv/app}/src/main.rs:12:3", expected_match: Some("${PROJECT_ROOT:-/srv/app}/src/main.rs:12:3"),
+                   description: "Path starting with ${VAR:-default} and line:col" },
+        TestCase { input: "costs $5/month", expected_match: None, description: "Dollar amount is not a variable" },
+
+        // Parenthesized locations (tsc, MSVC, C#, Fortran) and JVM frames
+        TestCase { input: "src/app.ts(10,5): error TS2304: Cannot find name 'x'.", expected_match: Some("src/app.ts(10,5)"),
+                   description: "tsc path(line,col)" },
+        TestCase { input: "./src/app.ts(10, 5): error", expected_match: Some("./src/app.ts(10, 5)"),
+                   description: "Prefixed path(line, col) with a space" },
+        TestCase { input: "File.cpp(42): error C2065: 'x': undeclared identifier", expected_match: Some("File.cpp(42)"),
+                   description: "MSVC bare file(line)" },
+        TestCase { input: "Program.cs(7,13): warning CS0168", expected_match: Some("Program.cs(7,13)"),
+                   description: "C# bare file(line,col)" },
+        TestCase { input: "/work/solver.f90(12): error #6404", expected_match: Some("/work/solver.f90(12)"),
+                   description: "Fortran absolute path(line)" },
+        TestCase { input: "\tat com.foo.Bar.baz(Bar.java:42)", expected_match: Some("Bar.java:42"),
+                   description: "JVM frame (File.java:line)" },
+        TestCase { input: "console.log(1)", expected_match: None, description: "Method call is not a location" },
+        TestCase { input: "Math.max(1, 2) returned", expected_match: None, description: "Call with arguments is not a location" },
+        TestCase { input: "see notes(1).txt", expected_match: None, description: "Parenthesized number inside a bare name" },
+
+        // Prose locations: the path is linked and the handler reads the line from the text
+        TestCase { input: "  File \"app/models/user.py\", line 42, in save", expected_match: Some("app/models/user.py"),
+                   description: "Python traceback frame" },
+        TestCase { input: "  File \"manage.py\", line 22, in <module>", expected_match: Some("manage.py"),
+                   description: "Python traceback frame with a bare file name" },
+        TestCase { input: "  on main.tf line 12, in resource \"aws_instance\" \"web\":", expected_match: Some("main.tf"),
+                   description: "Terraform diagnostic" },
+        TestCase { input: "In deploy.sh Line 9:", expected_match: Some("deploy.sh"), description: "shellcheck diagnostic" },
+        TestCase { input: "e.g. line 3 of the file", expected_match: None, description: "Abbreviation before a line in words" },
+
+        // V8/Node and JVM stack frames
+        TestCase { input: "    at handler (/app/src/x.js:10:5)", expected_match: Some("/app/src/x.js:10:5"),
+                   description: "Node frame with a function" },
+        TestCase { input: "    at async /app/x.js:3:1", expected_match: Some("/app/x.js:3:1"), description: "Node async frame" },
+        TestCase { input: "    at Server.listen (file:///app/server.mjs:7:9)", expected_match: Some("/app/server.mjs:7:9"),
+                   description: "Node ESM frame with a file:// URL" },
+        TestCase { input: "    at node:internal/main/run_main_module:28:49", expected_match: None, description: "Node internal frame" },
+        TestCase { input: "\tat com.foo.Bar$Inner.lambda$run$0(Bar.java:7)", expected_match: Some("Bar.java:7"),
+                   description: "JVM frame of a nested class lambda" },
+        TestCase { input: "\tat com.foo.UtilKt.main(Util.kt:3) ~[app.jar:?]", expected_match: Some("Util.kt:3"),
+                   description: "Kotlin frame with logback packaging" },
+        TestCase { input: "\tat java.lang.Object.wait(Native Method)", expected_match: None, description: "JVM native frame" },
+
+        // Line ranges
+        TestCase { input: "src/lib.rs:10-20", expected_match: Some("src/lib.rs:10-20"), description: "Line range" },
+        TestCase { input: "see src/lib.rs:10:5-12:8: unused", expected_match: Some("src/lib.rs:10:5-12:8"),
+                   description: "Line and column range" },
+        TestCase { input: "app/models/user.rb#L10-L20", expected_match: Some("app/models/user.rb#L10-L20"),
+                   description: "GitHub-style anchor range" },
+        TestCase { input: "(src/lib.rs#L10C5-L12C8)", expected_match: Some("src/lib.rs#L10C5-L12C8"),
+                   description: "GitHub-style anchor with columns in parentheses" },
+        TestCase { input: "./src/lib.rs:7-9", expected_match: Some("./src/lib.rs:7-9"), description: "Prefixed path with a range" },
     ];
 
     let mut total_passed = 0;